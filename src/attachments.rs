@@ -0,0 +1,170 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use lettre::message::{Attachment as AttachmentPart, SinglePart, header::ContentType};
+
+use crate::{merge, recipients::Recipient};
+
+/// 附件总大小超过该值时提示。多数服务商限制单封邮件在 20–25 MB，
+/// 而附件经 base64 编码后还会增大约三分之一。
+pub const SIZE_WARNING_BYTES: u64 = 15 * 1024 * 1024;
+
+/// 一个待发送的附件文件，内容在发送时才读取。
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    pub path: PathBuf,
+    /// 按扩展名推断的 MIME 类型，无法识别时为 `application/octet-stream`。
+    pub content_type: String,
+    pub size: u64,
+}
+
+impl Attachment {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let metadata =
+            fs::metadata(path).with_context(|| format!("读取附件失败: {}", path.display()))?;
+        if !metadata.is_file() {
+            anyhow::bail!("附件不是文件: {}", path.display());
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            content_type: mime_guess::from_path(path)
+                .first_or_octet_stream()
+                .to_string(),
+            size: metadata.len(),
+        })
+    }
+
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+
+    /// 读取文件内容，生成邮件中的附件部分。
+    pub fn to_part(&self) -> anyhow::Result<SinglePart> {
+        let content = fs::read(&self.path)
+            .with_context(|| format!("读取附件失败: {}", self.path.display()))?;
+        let content_type = ContentType::parse(&self.content_type)
+            .unwrap_or_else(|_| ContentType::parse("application/octet-stream").unwrap());
+        Ok(AttachmentPart::new(self.file_name()).body(content, content_type))
+    }
+}
+
+/// 按收件人数据展开个性化附件的路径模板 (如 `invoices/{{id}}.pdf`)，
+/// 相对路径以 `base_dir` 为基准。展开结果为空表示该收件人没有个性化附件。
+pub fn personal_path(
+    pattern: &str,
+    recipient: &Recipient,
+    base_dir: Option<&Path>,
+) -> Result<Option<PathBuf>, BTreeSet<String>> {
+    let rendered = merge::render(pattern, recipient)?;
+    let rendered = rendered.trim();
+    if rendered.is_empty() {
+        return Ok(None);
+    }
+
+    let path = PathBuf::from(rendered);
+    Ok(Some(match base_dir {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path,
+    }))
+}
+
+pub fn total_size(attachments: &[Attachment]) -> u64 {
+    attachments.iter().map(|a| a.size).sum()
+}
+
+/// 附件总大小超过 [`SIZE_WARNING_BYTES`] 时返回提示。
+pub fn size_warning(attachments: &[Attachment]) -> Option<String> {
+    let total = total_size(attachments);
+    (total > SIZE_WARNING_BYTES).then(|| {
+        format!(
+            "附件共 {}，编码后约 {}，可能超过邮件服务商的大小限制",
+            format_size(total),
+            format_size(total / 3 * 4)
+        )
+    })
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = 1024 * KB;
+    if bytes >= MB {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.1} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipient(fields: &[(&str, &str)]) -> Recipient {
+        let mut recipient = Recipient::new("alice@example.com");
+        for (key, value) in fields {
+            recipient.fields.insert(key.to_string(), value.to_string());
+        }
+        recipient
+    }
+
+    #[test]
+    fn merges_fields_into_the_path() {
+        let alice = recipient(&[("id", "42")]);
+        assert_eq!(
+            personal_path("invoices/{{id}}.pdf", &alice, None).unwrap(),
+            Some(PathBuf::from("invoices/42.pdf"))
+        );
+        assert_eq!(
+            personal_path("{{ missing }}.pdf", &alice, None).unwrap_err(),
+            BTreeSet::from(["missing".to_string()])
+        );
+    }
+
+    #[test]
+    fn resolves_relative_paths_against_base_dir() {
+        let base = Path::new("/templates");
+        let alice = recipient(&[("id", "42"), ("file", "/srv/a.pdf")]);
+        assert_eq!(
+            personal_path("invoices/{{id}}.pdf", &alice, Some(base)).unwrap(),
+            Some(base.join("invoices/42.pdf"))
+        );
+        assert_eq!(
+            personal_path("{{file}}", &alice, Some(base)).unwrap(),
+            Some(PathBuf::from("/srv/a.pdf"))
+        );
+    }
+
+    #[test]
+    fn blank_field_is_missing_and_blank_pattern_means_no_attachment() {
+        let alice = recipient(&[("file", "  ")]);
+        assert_eq!(
+            personal_path("{{file}}", &alice, None).unwrap_err(),
+            BTreeSet::from(["file".to_string()])
+        );
+        assert_eq!(personal_path("  ", &alice, None).unwrap(), None);
+    }
+
+    #[test]
+    fn loads_existing_files_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.pdf");
+        fs::write(&path, b"%PDF").unwrap();
+
+        let attachment = Attachment::load(&path).unwrap();
+        assert_eq!(attachment.size, 4);
+        assert_eq!(attachment.content_type, "application/pdf");
+        assert_eq!(attachment.file_name(), "report.pdf");
+
+        assert!(Attachment::load(&dir.path().join("gone.pdf")).is_err());
+        assert!(Attachment::load(dir.path()).is_err());
+    }
+}
@@ -0,0 +1,514 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use anyhow::Context;
+use batch_mail::{
+    attachments::{self, Attachment},
+    css_inline,
+    history::CampaignRecord,
+    inline_images,
+    mail_config::Profiles,
+    recipients,
+    sender::{self, BccBatch, Campaign, CampaignReport, SendControl, SendStatus},
+    vault::{self, Vault},
+};
+
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_ERROR: i32 = 1;
+pub const EXIT_PARTIAL: i32 = 2;
+pub const EXIT_ALL_FAILED: i32 = 3;
+pub const EXIT_QUEUED: i32 = 4;
+
+const USAGE: &str = "\
+用法:
+    batch_mail                          启动图形界面
+    batch_mail send [选项]              使用已保存的 SMTP 配置批量发送
+
+send 选项:
+    --html <文件>         HTML 正文模板
+    --subject <主题>      邮件主题，支持 {{name}} 等占位符
+    --text <文件>         手写的纯文本正文模板，默认由 HTML 自动转换
+    --inline-css          发送前把 <style> 中的 CSS 内联到元素上 (@media 等保留在 <style> 中)
+    --recipients <文件>   收件人列表 (.csv/.tsv 带表头，或每行一个地址的文本)
+    --attach <文件>       添加附件，可多次指定
+    --attach-each <路径>  每个收件人各自的附件，可用合并字段，如 invoices/{{id}}.pdf；
+                          相对路径以 HTML 模板所在目录为基准
+    --cc <地址>           抄送，多个地址以逗号分隔；收件人文件中的 cc 列会按人覆盖
+    --bcc <地址>          密送，收件人文件中的 bcc 列会按人覆盖
+    --reply-to <地址>     回复地址，收件人文件中的 reply_to 列会按人覆盖
+    --bcc-batch <人数>    密送群发：每批收件人合并为一封邮件，全部放在密送中；
+                          模板中不能使用合并字段，也不能使用 --cc/--bcc
+    --batch-to <地址>     密送群发时信头 To 显示的地址，默认为发件人自己
+    --skip-invalid        跳过无效地址，继续发送其余收件人
+    --profile <名称>      使用指定的账号配置，默认使用当前选用的账号
+    --test                只发送一封测试邮件到账号配置的测试收件人 (默认发给自己)，
+                          用第一个收件人的数据合并，主题加 [TEST] 前缀
+
+环境变量:
+    BATCH_MAIL_PASSPHRASE 解锁密码库的主密码；未设置时从标准输入读取
+
+退出码:
+    0  全部发送成功
+    1  参数、配置或模板错误，未发送任何邮件
+    2  部分发送失败
+    3  全部发送失败
+    4  没有发送失败，但已达每日上限，部分收件人已排队 (配额恢复后可在发送记录中重试)";
+
+struct SendArgs {
+    html: PathBuf,
+    text: Option<PathBuf>,
+    subject: String,
+    recipients: PathBuf,
+    attachments: Vec<PathBuf>,
+    personal_attachment: Option<String>,
+    cc: String,
+    bcc: String,
+    reply_to: String,
+    bcc_batch: Option<usize>,
+    batch_to: String,
+    inline_css: bool,
+    skip_invalid: bool,
+    profile: Option<String>,
+    test: bool,
+}
+
+fn parse_send_args(args: &[String]) -> anyhow::Result<SendArgs> {
+    let mut html = None;
+    let mut text = None;
+    let mut subject = None;
+    let mut recipients = None;
+    let mut attachments = Vec::new();
+    let mut personal_attachment = None;
+    let mut cc = String::new();
+    let mut bcc = String::new();
+    let mut reply_to = String::new();
+    let mut bcc_batch = None;
+    let mut batch_to = String::new();
+    let mut inline_css = false;
+    let mut skip_invalid = false;
+    let mut profile = None;
+    let mut test = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .with_context(|| format!("参数 {} 缺少取值", arg))
+        };
+        match arg.as_str() {
+            "--html" => html = Some(PathBuf::from(value()?)),
+            "--subject" => subject = Some(value()?),
+            "--text" => text = Some(PathBuf::from(value()?)),
+            "--recipients" => recipients = Some(PathBuf::from(value()?)),
+            "--attach" => attachments.push(PathBuf::from(value()?)),
+            "--attach-each" => personal_attachment = Some(value()?),
+            "--cc" => cc = value()?,
+            "--bcc" => bcc = value()?,
+            "--reply-to" => reply_to = value()?,
+            "--bcc-batch" => {
+                let size = value()?
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|size| *size > 0)
+                    .context("--bcc-batch 需要一个正整数")?;
+                bcc_batch = Some(size);
+            }
+            "--batch-to" => batch_to = value()?,
+            "--skip-invalid" => skip_invalid = true,
+            "--inline-css" => inline_css = true,
+            "--profile" => profile = Some(value()?),
+            "--test" => test = true,
+            other => anyhow::bail!("未知参数: {}", other),
+        }
+    }
+
+    Ok(SendArgs {
+        html: html.context("缺少 --html 参数")?,
+        text,
+        subject: subject.context("缺少 --subject 参数")?,
+        recipients: recipients.context("缺少 --recipients 参数")?,
+        attachments,
+        personal_attachment,
+        cc,
+        bcc,
+        reply_to,
+        bcc_batch,
+        batch_to,
+        inline_css,
+        skip_invalid,
+        profile,
+        test,
+    })
+}
+
+/// 命令行入口，返回进程退出码。
+pub fn run(args: &[String]) -> i32 {
+    match args.first().map(|s| s.as_str()) {
+        Some("send") => match send(&args[1..]) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("错误: {:#}", e);
+                EXIT_ERROR
+            }
+        },
+        Some("-h" | "--help" | "help") => {
+            println!("{}", USAGE);
+            EXIT_SUCCESS
+        }
+        Some(other) => {
+            eprintln!("未知命令: {}\n\n{}", other, USAGE);
+            EXIT_ERROR
+        }
+        None => EXIT_SUCCESS,
+    }
+}
+
+fn read_passphrase() -> anyhow::Result<String> {
+    if let Ok(passphrase) = std::env::var(vault::PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+
+    let passphrase = prompt_hidden("主密码: ")?;
+    if !Vault::exists() && prompt_hidden("再次输入主密码: ")? != passphrase {
+        anyhow::bail!("两次输入的主密码不一致");
+    }
+    Ok(passphrase)
+}
+
+/// 从标准输入读取一行；标准输入是终端时关闭回显。
+fn prompt_hidden(prompt: &str) -> anyhow::Result<String> {
+    let echo = EchoGuard::disable();
+    eprint!("{}", prompt);
+    io::stderr().flush().ok();
+
+    let mut line = String::new();
+    let result = io::stdin().read_line(&mut line);
+    if echo.is_some() {
+        eprintln!();
+    }
+    drop(echo);
+
+    result.context("读取主密码失败")?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// 关闭终端回显，离开作用域时恢复原有设置。
+#[cfg(unix)]
+struct EchoGuard(libc::termios);
+
+#[cfg(unix)]
+impl EchoGuard {
+    fn disable() -> Option<Self> {
+        // SAFETY: termios 是纯数据结构，tcgetattr 成功后才会使用其内容。
+        unsafe {
+            let mut original: libc::termios = std::mem::zeroed();
+            if libc::isatty(libc::STDIN_FILENO) == 0
+                || libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0
+            {
+                return None;
+            }
+            let mut hidden = original;
+            hidden.c_lflag &= !libc::ECHO;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &hidden) != 0 {
+                return None;
+            }
+            Some(Self(original))
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for EchoGuard {
+    fn drop(&mut self) {
+        // SAFETY: 恢复 disable 时读取到的终端设置。
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.0);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct EchoGuard;
+
+#[cfg(not(unix))]
+impl EchoGuard {
+    fn disable() -> Option<Self> {
+        None
+    }
+}
+
+fn send(args: &[String]) -> anyhow::Result<i32> {
+    let args = parse_send_args(args)?;
+
+    let html_body = fs::read_to_string(&args.html)
+        .with_context(|| format!("读取 HTML 文件失败: {}", args.html.display()))?;
+    let text_body = args
+        .text
+        .as_ref()
+        .map(|path| {
+            fs::read_to_string(path)
+                .with_context(|| format!("读取纯文本模板失败: {}", path.display()))
+        })
+        .transpose()?;
+    let (recipients, rejected) = recipients::load_recipients_file(&args.recipients)?;
+    for row in &rejected {
+        eprintln!("跳过第 {} 行: {}", row.line, row.reason);
+    }
+
+    let attachments = args
+        .attachments
+        .iter()
+        .map(|path| Attachment::load(&fs::canonicalize(path).unwrap_or_else(|_| path.clone())))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if let Some(warning) = attachments::size_warning(&attachments) {
+        eprintln!("警告: {}", warning);
+    }
+
+    let mut vault = Vault::unlock(&read_passphrase()?).context("解锁密码库失败")?;
+    let config = Profiles::load_unlocked(&mut vault)
+        .and_then(|profiles| profiles.config(args.profile.as_deref()))
+        .context("加载配置失败")?;
+    let template_path = fs::canonicalize(&args.html).unwrap_or(args.html);
+    let mut campaign = Campaign::new(config, recipients, args.subject, html_body)
+        .text_body(text_body)
+        .attachments(attachments)
+        .personal_attachment(args.personal_attachment)
+        .base_dir(template_path.parent().map(|dir| dir.to_path_buf()))
+        .cc(args.cc)
+        .bcc(args.bcc)
+        .reply_to(args.reply_to)
+        .bcc_batch(args.bcc_batch.map(|size| BccBatch {
+            size,
+            visible_to: args.batch_to,
+        }))
+        .inline_css(args.inline_css)
+        .skip_invalid(args.skip_invalid);
+    if args.test {
+        campaign = campaign.test_copy()?;
+    }
+    campaign.validate()?;
+    if campaign.inline_css {
+        let report = css_inline::inline(&campaign.html_body);
+        println!("CSS 已内联到 {} 个元素", report.styled_elements);
+        for warning in report.warnings() {
+            eprintln!("警告: {}", warning);
+        }
+    }
+    for image in inline_images::missing_images(&campaign.html_body, campaign.base_dir.as_deref()) {
+        eprintln!(
+            "警告: 找不到图片 {} ({})，将保持原样",
+            image.src,
+            image.path.display()
+        );
+    }
+
+    let total = campaign.recipients.len();
+    match &campaign.bcc_batch {
+        Some(batch) => println!(
+            "开始密送群发，共 {} 个收件人，每 {} 人一封",
+            total, batch.size
+        ),
+        None => println!("开始发送，共 {} 个收件人", total),
+    }
+
+    let control = SendControl::new();
+    let mut done = 0;
+    let report =
+        sender::runtime().block_on(campaign.send_with_progress(&control, |_, result| {
+            done += 1;
+            let tag = match &result.status {
+                SendStatus::Sent { .. } => "成功",
+                SendStatus::Failed { .. } => "失败",
+                SendStatus::Skipped { .. } => "跳过",
+                SendStatus::Queued => "排队",
+                SendStatus::Cancelled => "取消",
+            };
+            println!(
+                "[{}/{}] {} {} ({}, {:.1}s)",
+                done,
+                total,
+                tag,
+                result.email,
+                result.status.detail(),
+                result.elapsed.as_secs_f64()
+            );
+        }))?;
+
+    println!("{}", report.summary());
+    if !args.test
+        && let Err(e) = CampaignRecord::new(&campaign, Some(template_path), &report).save()
+    {
+        eprintln!("保存发送记录失败: {:#}", e);
+    }
+
+    Ok(exit_code(&report))
+}
+
+/// 按发送结果选择退出码，含义见 [`USAGE`]。跳过的无效地址和取消的收件人都算作未发送成功。
+fn exit_code(report: &CampaignReport) -> i32 {
+    let (sent, queued) = (report.sent_count(), report.queued_count());
+    if sent + queued == report.results.len() {
+        if queued == 0 {
+            EXIT_SUCCESS
+        } else {
+            EXIT_QUEUED
+        }
+    } else if sent == 0 && queued == 0 {
+        EXIT_ALL_FAILED
+    } else {
+        EXIT_PARTIAL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use batch_mail::sender::RecipientResult;
+
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    const REQUIRED: [&str; 6] = [
+        "--html",
+        "mail.html",
+        "--subject",
+        "Hi",
+        "--recipients",
+        "list.csv",
+    ];
+
+    fn parse(extra: &[&str]) -> anyhow::Result<SendArgs> {
+        let mut list = REQUIRED.to_vec();
+        list.extend_from_slice(extra);
+        parse_send_args(&args(&list))
+    }
+
+    fn error(result: anyhow::Result<SendArgs>) -> String {
+        result.err().expect("expected an error").to_string()
+    }
+
+    #[test]
+    fn parses_send_arguments() {
+        let parsed = parse(&[
+            "--attach",
+            "a.pdf",
+            "--attach",
+            "b.pdf",
+            "--bcc-batch",
+            "50",
+            "--batch-to",
+            "list@x.com",
+            "--skip-invalid",
+            "--test",
+        ])
+        .unwrap();
+
+        assert_eq!(parsed.html, PathBuf::from("mail.html"));
+        assert_eq!(parsed.subject, "Hi");
+        assert_eq!(parsed.recipients, PathBuf::from("list.csv"));
+        assert_eq!(
+            parsed.attachments,
+            [PathBuf::from("a.pdf"), PathBuf::from("b.pdf")]
+        );
+        assert_eq!(parsed.bcc_batch, Some(50));
+        assert_eq!(parsed.batch_to, "list@x.com");
+        assert!(parsed.skip_invalid && parsed.test);
+        assert!(!parsed.inline_css);
+        assert_eq!(parsed.profile, None);
+    }
+
+    #[test]
+    fn rejects_missing_values_and_arguments() {
+        assert_eq!(error(parse(&["--profile"])), "参数 --profile 缺少取值");
+        assert_eq!(
+            error(parse_send_args(&args(&[
+                "--html",
+                "a.html",
+                "--subject",
+                "Hi"
+            ]))),
+            "缺少 --recipients 参数"
+        );
+        assert_eq!(error(parse_send_args(&[])), "缺少 --html 参数");
+    }
+
+    #[test]
+    fn rejects_unknown_arguments() {
+        assert_eq!(error(parse(&["--dry-run"])), "未知参数: --dry-run");
+    }
+
+    #[test]
+    fn bcc_batch_needs_a_positive_size() {
+        for size in ["0", "-1", "ten"] {
+            assert_eq!(
+                error(parse(&["--bcc-batch", size])),
+                "--bcc-batch 需要一个正整数"
+            );
+        }
+    }
+
+    fn report(statuses: &[SendStatus]) -> CampaignReport {
+        CampaignReport {
+            results: statuses
+                .iter()
+                .enumerate()
+                .map(|(i, status)| RecipientResult {
+                    email: format!("r{}@x.com", i),
+                    status: status.clone(),
+                    elapsed: Duration::ZERO,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn maps_reports_to_exit_codes() {
+        let sent = SendStatus::Sent {
+            response: "250 OK".to_string(),
+        };
+        let failed = SendStatus::Failed {
+            error: "550".to_string(),
+            smtp_code: Some(550),
+            enhanced_code: None,
+            attempts: 1,
+        };
+        let skipped = SendStatus::Skipped {
+            reason: "地址格式错误".to_string(),
+        };
+
+        assert_eq!(
+            exit_code(&report(&[sent.clone(), sent.clone()])),
+            EXIT_SUCCESS
+        );
+        assert_eq!(
+            exit_code(&report(&[sent.clone(), SendStatus::Queued])),
+            EXIT_QUEUED
+        );
+        assert_eq!(exit_code(&report(&[SendStatus::Queued])), EXIT_QUEUED);
+        assert_eq!(
+            exit_code(&report(&[sent.clone(), failed.clone()])),
+            EXIT_PARTIAL
+        );
+        assert_eq!(
+            exit_code(&report(&[sent.clone(), skipped.clone()])),
+            EXIT_PARTIAL
+        );
+        assert_eq!(
+            exit_code(&report(&[failed.clone(), SendStatus::Queued])),
+            EXIT_PARTIAL
+        );
+        assert_eq!(exit_code(&report(&[failed, skipped])), EXIT_ALL_FAILED);
+        assert_eq!(
+            exit_code(&report(&[SendStatus::Cancelled])),
+            EXIT_ALL_FAILED
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use lettre::transport::smtp::{
+    authentication::{Credentials, Mechanism},
+    client::{AsyncSmtpConnection, AsyncTokioStream, TlsParameters},
+    commands::Ehlo,
+    extension::ClientId,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+};
+
+use crate::mail_config::{MailConfig, TlsMode};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// 整个测试的时限。加密方式与端口不匹配时 (如对 465 端口使用 STARTTLS)，
+/// 双方都在等对方先发数据，只能靠超时结束。
+const TEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 一次连接测试的结果；出错时保留出错前已经拿到的信息。
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionReport {
+    /// 服务器连接后发来的问候 (220 响应)，按原样保留每一行。
+    pub greeting: Vec<String>,
+    /// 服务器在 EHLO 响应中报出的主机名。
+    pub server_name: String,
+    /// 完整的 EHLO 响应，加密后重新获取。
+    pub ehlo_lines: Vec<String>,
+    pub encrypted: bool,
+    /// 服务器声明支持的 AUTH 机制，原样列出 (包括本程序不支持的)。
+    pub auth_mechanisms: Vec<String>,
+    /// 认证成功时服务器的响应。
+    pub auth_response: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 连接服务器、完成 TLS 握手并认证，不发送任何邮件。
+pub async fn test_connection(config: &MailConfig) -> ConnectionReport {
+    test_connection_within(config, TEST_TIMEOUT).await
+}
+
+async fn test_connection_within(config: &MailConfig, limit: Duration) -> ConnectionReport {
+    let mut report = ConnectionReport::default();
+    let error = match tokio::time::timeout(limit, run(config, &mut report)).await {
+        Ok(result) => result.err(),
+        Err(_) => Some(format!(
+            "连接测试超时: {} 秒内未完成，请检查端口与加密方式是否匹配",
+            limit.as_secs()
+        )),
+    };
+    report.error = error;
+    report
+}
+
+async fn run(config: &MailConfig, report: &mut ConnectionReport) -> Result<(), String> {
+    let host = config.smtp_server.trim();
+    if host.is_empty() {
+        return Err("SMTP 服务器不能为空".to_string());
+    }
+
+    let hello = ClientId::default();
+    let tls_parameters =
+        || TlsParameters::new(host.to_string()).map_err(|e| format!("TLS 参数错误: {}", e));
+
+    let greeting = Arc::new(Mutex::new(Vec::new()));
+    let stream = connect(host, config, greeting.clone()).await?;
+    let connected = AsyncSmtpConnection::connect_with_transport(stream, &hello).await;
+    report.greeting = response_lines(&greeting.lock().unwrap());
+    let mut conn = connected.map_err(|e| format!("连接失败: {}", e))?;
+    report.server_name = conn.server_info().name().to_string();
+
+    let upgrade = match config.tls_mode {
+        TlsMode::StartTlsRequired if !conn.can_starttls() => {
+            return Err("服务器不支持 STARTTLS".to_string());
+        }
+        TlsMode::StartTlsRequired => true,
+        TlsMode::StartTlsOpportunistic => conn.can_starttls(),
+        TlsMode::Wrapper | TlsMode::None => false,
+    };
+    if upgrade {
+        conn.starttls(tls_parameters()?, &hello)
+            .await
+            .map_err(|e| format!("STARTTLS 失败: {}", e))?;
+    }
+    // 隐式 TLS 由 `connect` 自行握手，lettre 把它当作普通 TCP 连接。
+    report.encrypted = conn.is_encrypted() || config.tls_mode == TlsMode::Wrapper;
+
+    let ehlo = conn
+        .command(Ehlo::new(hello.clone()))
+        .await
+        .map_err(|e| format!("EHLO 失败: {}", e))?;
+    report.ehlo_lines = ehlo.message().map(|line| line.to_string()).collect();
+    report.auth_mechanisms = report
+        .ehlo_lines
+        .iter()
+        .filter_map(|line| {
+            let rest = line.strip_prefix("AUTH")?;
+            rest.starts_with([' ', '=']).then(|| rest[1..].to_string())
+        })
+        .flat_map(|rest| {
+            rest.split_whitespace()
+                .map(|m| m.to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    if config.password.is_empty() {
+        conn.quit().await.ok();
+        return Err("未填写密码，已跳过认证".to_string());
+    }
+    let credentials = Credentials::new(config.email_address.clone(), config.password.clone());
+    let response = conn
+        .auth(&[Mechanism::Plain, Mechanism::Login], &credentials)
+        .await
+        .map_err(|e| format!("认证失败: {}", e))?;
+    report.auth_response = Some(format!(
+        "{} {}",
+        response.code(),
+        response.first_line().unwrap_or_default()
+    ));
+
+    conn.quit().await.ok();
+    Ok(())
+}
+
+/// 建立 TCP 连接 (隐式 TLS 时同时完成握手)，并套上记录问候的包装。
+///
+/// lettre 的 `connect_tokio1` 读取问候后直接丢弃，因此自行建立连接再交给 lettre。
+async fn connect(
+    host: &str,
+    config: &MailConfig,
+    greeting: Arc<Mutex<Vec<u8>>>,
+) -> Result<Box<dyn AsyncTokioStream>, String> {
+    let tcp = tokio::time::timeout(
+        CONNECT_TIMEOUT,
+        TcpStream::connect((host, config.smtp_port)),
+    )
+    .await
+    .map_err(|_| "连接失败: 连接超时".to_string())?
+    .map_err(|e| format!("连接失败: {}", e))?;
+    let peer = tcp.peer_addr().map_err(|e| format!("连接失败: {}", e))?;
+
+    if config.tls_mode != TlsMode::Wrapper {
+        return Ok(Box::new(GreetingRecorder::new(tcp, peer, greeting)));
+    }
+    let connector = native_tls::TlsConnector::new().map_err(|e| format!("TLS 参数错误: {}", e))?;
+    let tls = tokio::time::timeout(
+        CONNECT_TIMEOUT,
+        tokio_native_tls::TlsConnector::from(connector).connect(host, tcp),
+    )
+    .await
+    .map_err(|_| "TLS 握手失败: 连接超时".to_string())?
+    .map_err(|e| format!("TLS 握手失败: {}", e))?;
+    Ok(Box::new(GreetingRecorder::new(tls, peer, greeting)))
+}
+
+/// 把记录下的原始响应拆成行。
+fn response_lines(raw: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(raw)
+        .lines()
+        .map(|line| line.trim_end().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// 响应的最后一行是状态码后跟空格 (或只有状态码) 的那一行。
+fn is_complete_response(raw: &[u8]) -> bool {
+    raw.split(|b| *b == b'\n')
+        .filter(|line| !line.is_empty())
+        .any(|line| line.len() <= 4 || line[3] != b'-')
+        && raw.ends_with(b"\n")
+}
+
+/// 转发读写的流包装，把服务器发来的第一条完整响应 (即问候) 记录下来。
+#[derive(Debug)]
+struct GreetingRecorder<S> {
+    inner: S,
+    peer: SocketAddr,
+    greeting: Arc<Mutex<Vec<u8>>>,
+}
+
+impl<S> GreetingRecorder<S> {
+    fn new(inner: S, peer: SocketAddr, greeting: Arc<Mutex<Vec<u8>>>) -> Self {
+        Self {
+            inner,
+            peer,
+            greeting,
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for GreetingRecorder<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let start = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if poll.is_ready() {
+            let mut greeting = self.greeting.lock().unwrap();
+            if !is_complete_response(&greeting) {
+                greeting.extend_from_slice(&buf.filled()[start..]);
+            }
+        }
+        poll
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for GreetingRecorder<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl<S> AsyncTokioStream for GreetingRecorder<S>
+where
+    S: AsyncRead + AsyncWrite + Send + Sync + Unpin + std::fmt::Debug,
+{
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.peer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn silent_server_times_out() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (_stream, _) = listener.accept().await.unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        let config = MailConfig {
+            smtp_server: "127.0.0.1".to_string(),
+            smtp_port: port,
+            tls_mode: TlsMode::StartTlsRequired,
+            ..MailConfig::default()
+        };
+        let report = test_connection_within(&config, Duration::from_millis(300)).await;
+        server.abort();
+
+        assert!(report.greeting.is_empty());
+        assert!(report.error.unwrap().contains("请检查端口与加密方式"));
+    }
+}
@@ -0,0 +1,570 @@
+use std::ops::Range;
+
+use simplecss::{AttributeOperator, DeclarationTokenizer, Element, PseudoClass, Selector};
+
+use crate::html::{self, Tag, Token};
+
+/// 没有结束标签、不会包含子元素的元素。
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// CSS 内联的结果。
+#[derive(Debug, Clone, Default)]
+pub struct InlineReport {
+    pub html: String,
+    /// `style` 属性被改写的元素数。
+    pub styled_elements: usize,
+    /// 原样保留在 `<style>` 中的 at 规则数，如 `@media` 查询。
+    pub kept_at_rules: usize,
+    /// 带伪类、伪元素或无法解析，只能保留在 `<style>` 中的选择器。
+    pub not_inlined: Vec<String>,
+    /// 没有匹配到任何元素的选择器。
+    pub unmatched: Vec<String>,
+}
+
+impl InlineReport {
+    /// 供界面和命令行显示的提示，每条一行。
+    pub fn warnings(&self) -> Vec<String> {
+        let not_inlined = self
+            .not_inlined
+            .iter()
+            .map(|selector| format!("无法内联，已保留在 <style> 中: {}", selector));
+        let unmatched = self
+            .unmatched
+            .iter()
+            .map(|selector| format!("没有匹配的元素: {}", selector));
+        not_inlined.chain(unmatched).collect()
+    }
+}
+
+/// 样式表中的一项。
+enum CssItem<'a> {
+    /// `@media`、`@font-face` 等，原样保留。
+    AtRule(&'a str),
+    Rule {
+        selectors: &'a str,
+        declarations: &'a str,
+    },
+}
+
+/// `i` 处是合并字段 `{{...}}` 时返回其后的位置。
+fn placeholder_end(css: &str, i: usize) -> Option<usize> {
+    if !css[i..].starts_with("{{") {
+        return None;
+    }
+    css[i + 2..].find("}}").map(|j| i + 2 + j + 2)
+}
+
+/// 把样式表拆成顶层的规则和 at 规则，注释会被丢弃。合并字段中的花括号不参与配对。
+fn split_css(css: &str) -> Vec<CssItem<'_>> {
+    let bytes = css.as_bytes();
+    let mut items = Vec::new();
+    let mut i = 0;
+
+    // 从 `start` 开始找到第一个不属于合并字段的 `{` 或 `;`。
+    let find_open = |start: usize| {
+        let mut j = start;
+        while j < bytes.len() {
+            if let Some(end) = placeholder_end(css, j) {
+                j = end;
+                continue;
+            }
+            if matches!(bytes[j], b'{' | b';') {
+                return Some(j);
+            }
+            j += 1;
+        }
+        None
+    };
+
+    // 从 `start` (指向 `{`) 开始找到匹配的 `}`，返回其后的位置。
+    let block_end = |start: usize| {
+        let mut depth = 0usize;
+        let mut j = start;
+        while j < bytes.len() {
+            if let Some(end) = placeholder_end(css, j).filter(|_| depth > 0) {
+                j = end;
+                continue;
+            }
+            match bytes[j] {
+                b'{' => depth += 1,
+                b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return j + 1;
+                    }
+                }
+                _ => {}
+            }
+            j += 1;
+        }
+        bytes.len()
+    };
+
+    while i < bytes.len() {
+        if bytes[i].is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if css[i..].starts_with("/*") {
+            i = css[i + 2..]
+                .find("*/")
+                .map(|j| i + 2 + j + 2)
+                .unwrap_or(bytes.len());
+            continue;
+        }
+        // `<!--` 和 `-->` 在 `<style>` 中只是兼容旧浏览器的写法。
+        if css[i..].starts_with("<!--") {
+            i += 4;
+            continue;
+        }
+        if css[i..].starts_with("-->") {
+            i += 3;
+            continue;
+        }
+
+        let start = i;
+        let Some(open) = find_open(i) else {
+            break;
+        };
+        if bytes[start] == b'@' {
+            let end = if bytes[open] == b';' {
+                open + 1
+            } else {
+                block_end(open)
+            };
+            items.push(CssItem::AtRule(css[start..end].trim()));
+            i = end;
+        } else if bytes[open] == b'{' {
+            let end = block_end(open);
+            let close = if css[..end].ends_with('}') {
+                end - 1
+            } else {
+                end
+            };
+            items.push(CssItem::Rule {
+                selectors: css[start..open].trim(),
+                declarations: css[open + 1..close].trim(),
+            });
+            i = end;
+        } else {
+            // 规则外多余的分号。
+            i = open + 1;
+        }
+    }
+    items
+}
+
+/// 是否可以按元素匹配后写进 `style` 属性。伪类 (`:first-child` 除外) 和伪元素依赖交互或渲染状态，
+/// 只能留在 `<style>` 中。
+fn is_inlinable(selector: &str) -> bool {
+    !selector.is_empty() && !selector.replace(":first-child", "").contains(':')
+}
+
+struct Node {
+    tag: Tag,
+    range: Range<usize>,
+    parent: Option<usize>,
+    prev_sibling: Option<usize>,
+}
+
+/// 由标签序列恢复出的元素树，只记录选择器匹配需要的父元素和前一个兄弟元素。
+fn build_tree(tokens: &[(Range<usize>, Token<'_>)]) -> Vec<Node> {
+    let mut nodes: Vec<Node> = Vec::new();
+    let mut open: Vec<usize> = Vec::new();
+    // 目前的最后一个子元素：第 0 位是根，第 i + 1 位是第 i 个元素。
+    let mut last_child: Vec<Option<usize>> = vec![None];
+
+    for (range, token) in tokens {
+        let Token::Tag(tag) = token else {
+            continue;
+        };
+
+        if tag.closing {
+            if let Some(pos) = open.iter().rposition(|&i| nodes[i].tag.name == tag.name) {
+                open.truncate(pos);
+            }
+            continue;
+        }
+
+        let index = nodes.len();
+        let parent = open.last().copied();
+        let slot = parent.map(|p| p + 1).unwrap_or(0);
+        nodes.push(Node {
+            tag: tag.clone(),
+            range: range.clone(),
+            parent,
+            prev_sibling: last_child[slot],
+        });
+        last_child[slot] = Some(index);
+        last_child.push(None);
+
+        if !tag.self_closing && !VOID_ELEMENTS.contains(&tag.name.as_str()) {
+            open.push(index);
+        }
+    }
+    nodes
+}
+
+#[derive(Clone, Copy)]
+struct NodeRef<'a> {
+    nodes: &'a [Node],
+    index: usize,
+}
+
+impl NodeRef<'_> {
+    fn tag(&self) -> &Tag {
+        &self.nodes[self.index].tag
+    }
+
+    fn at(&self, index: Option<usize>) -> Option<Self> {
+        index.map(|index| NodeRef {
+            nodes: self.nodes,
+            index,
+        })
+    }
+}
+
+impl Element for NodeRef<'_> {
+    fn parent_element(&self) -> Option<Self> {
+        self.at(self.nodes[self.index].parent)
+    }
+
+    fn prev_sibling_element(&self) -> Option<Self> {
+        self.at(self.nodes[self.index].prev_sibling)
+    }
+
+    fn has_local_name(&self, name: &str) -> bool {
+        self.tag().name.eq_ignore_ascii_case(name)
+    }
+
+    fn attribute_matches(&self, local_name: &str, operator: AttributeOperator<'_>) -> bool {
+        self.tag()
+            .attr(local_name)
+            .is_some_and(|value| operator.matches(value))
+    }
+
+    fn pseudo_class_matches(&self, class: PseudoClass<'_>) -> bool {
+        match class {
+            PseudoClass::FirstChild => self.prev_sibling_element().is_none(),
+            _ => false,
+        }
+    }
+}
+
+/// 一条准备写进 `style` 的声明，按 (重要性, 特异性, 出现顺序) 决定覆盖关系。
+struct Applied {
+    important: bool,
+    specificity: [u8; 3],
+    order: usize,
+    name: String,
+    value: String,
+}
+
+/// 把 `<style>` 中的规则内联到匹配元素的 `style` 属性上。
+///
+/// `@media` 等 at 规则和带伪类的规则无法内联，合并成一个 `<style>` 放在原先第一个
+/// `<style>` 的位置 (通常在 `<head>` 中)；元素上已有的 `style` 优先于样式表。
+/// 没有改动的标签保持原文不变。
+pub fn inline(source: &str) -> InlineReport {
+    let tokens = html::tokenize(source);
+
+    // 每个 `<style>` 块从开始标签到结束标签的范围，以及其中的 CSS 文本。
+    let mut blocks: Vec<(Range<usize>, &str)> = Vec::new();
+    let mut iter = tokens.iter().peekable();
+    while let Some((range, token)) = iter.next() {
+        let Token::Tag(tag) = token else {
+            continue;
+        };
+        if tag.name != "style" || tag.closing || tag.self_closing {
+            continue;
+        }
+        let css = match iter.peek() {
+            Some((_, Token::Text(text))) => {
+                iter.next();
+                *text
+            }
+            _ => "",
+        };
+        let end = match iter.peek() {
+            Some((close, Token::Tag(t))) if t.name == "style" && t.closing => {
+                let end = close.end;
+                iter.next();
+                end
+            }
+            _ => range.end + css.len(),
+        };
+        blocks.push((range.start..end, css));
+    }
+
+    if blocks.is_empty() {
+        return InlineReport {
+            html: source.to_string(),
+            ..Default::default()
+        };
+    }
+
+    let nodes = build_tree(&tokens);
+    let mut applied: Vec<Vec<Applied>> = (0..nodes.len()).map(|_| Vec::new()).collect();
+    let mut report = InlineReport::default();
+    // 保留在 `<style>` 中的 CSS 文本。
+    let mut kept: Vec<String> = Vec::new();
+    let mut order = 0;
+
+    for item in blocks.iter().flat_map(|(_, css)| split_css(css)) {
+        let (selectors, declarations) = match item {
+            CssItem::AtRule(rule) => {
+                kept.push(rule.to_string());
+                report.kept_at_rules += 1;
+                continue;
+            }
+            CssItem::Rule {
+                selectors,
+                declarations,
+            } => (selectors, declarations),
+        };
+
+        // 含合并字段的规则要到逐个收件人合并后才有确定的值，原样保留。
+        if selectors.contains("{{") || declarations.contains("{{") {
+            kept.push(format!("{} {{ {} }}", selectors, declarations));
+            report
+                .not_inlined
+                .push(format!("{} (含合并字段)", selectors));
+            continue;
+        }
+
+        let declarations: Vec<_> = DeclarationTokenizer::from(declarations).collect();
+        for selector_text in selectors.split(',').map(str::trim) {
+            let selector = is_inlinable(selector_text)
+                .then(|| Selector::parse(selector_text))
+                .flatten();
+            let Some(selector) = selector else {
+                kept.push(format!(
+                    "{} {{ {} }}",
+                    selector_text,
+                    declarations_text(&declarations)
+                ));
+                report.not_inlined.push(selector_text.to_string());
+                continue;
+            };
+
+            let specificity = selector.specificity();
+            let mut matched = false;
+            for (index, rules) in applied.iter_mut().enumerate() {
+                // `<head>` 中的元素和脚本、样式不会显示，不需要样式。
+                let name = nodes[index].tag.name.as_str();
+                if matches!(name, "head" | "style" | "script") || is_in_head(&nodes, index) {
+                    continue;
+                }
+                if !selector.matches(&NodeRef {
+                    nodes: &nodes,
+                    index,
+                }) {
+                    continue;
+                }
+                matched = true;
+                for declaration in &declarations {
+                    rules.push(Applied {
+                        important: declaration.important,
+                        specificity,
+                        order,
+                        name: declaration.name.to_string(),
+                        value: declaration.value.to_string(),
+                    });
+                    order += 1;
+                }
+            }
+            if !matched {
+                report.unmatched.push(selector_text.to_string());
+            }
+        }
+    }
+
+    // 逐个替换改动过的标签和 `<style>` 块，其余内容按原文拷贝。
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    for (index, mut rules) in applied.into_iter().enumerate() {
+        if rules.is_empty() {
+            continue;
+        }
+        rules.sort_by_key(|r| (r.important, r.specificity, r.order));
+
+        let mut style: Vec<(String, String)> = Vec::new();
+        let mut set = |name: &str, value: String| match style.iter_mut().find(|(n, _)| n == name) {
+            Some((_, existing)) => *existing = value,
+            None => style.push((name.to_string(), value)),
+        };
+        let existing: Vec<_> = nodes[index]
+            .tag
+            .attr("style")
+            .map(|s| DeclarationTokenizer::from(s).collect())
+            .unwrap_or_default();
+
+        for rule in rules.iter().filter(|r| !r.important) {
+            set(&rule.name, rule.value.clone());
+        }
+        for declaration in &existing {
+            let value = if declaration.important {
+                format!("{} !important", declaration.value)
+            } else {
+                declaration.value.to_string()
+            };
+            set(declaration.name, value);
+        }
+        for rule in rules.iter().filter(|r| r.important) {
+            let inline_important = existing.iter().any(|d| d.important && d.name == rule.name);
+            if !inline_important {
+                set(&rule.name, format!("{} !important", rule.value));
+            }
+        }
+
+        let style = style
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let mut tag = nodes[index].tag.clone();
+        tag.set_attr("style", style);
+        edits.push((nodes[index].range.clone(), tag.to_html()));
+        report.styled_elements += 1;
+    }
+
+    for (i, (range, _)) in blocks.iter().enumerate() {
+        let replacement = if i == 0 && !kept.is_empty() {
+            format!("<style>\n{}\n</style>", kept.join("\n"))
+        } else {
+            String::new()
+        };
+        edits.push((range.clone(), replacement));
+    }
+    edits.sort_by_key(|(range, _)| range.start);
+
+    let mut output = String::with_capacity(source.len());
+    let mut last = 0;
+    for (range, replacement) in edits {
+        if range.start < last {
+            continue;
+        }
+        output.push_str(&source[last..range.start]);
+        output.push_str(&replacement);
+        last = range.end;
+    }
+    output.push_str(&source[last..]);
+    report.html = output;
+    report
+}
+
+fn is_in_head(nodes: &[Node], index: usize) -> bool {
+    let mut parent = nodes[index].parent;
+    while let Some(p) = parent {
+        if nodes[p].tag.name == "head" {
+            return true;
+        }
+        parent = nodes[p].parent;
+    }
+    false
+}
+
+fn declarations_text(declarations: &[simplecss::Declaration<'_>]) -> String {
+    declarations
+        .iter()
+        .map(|d| {
+            if d.important {
+                format!("{}: {} !important;", d.name, d.value)
+            } else {
+                format!("{}: {};", d.name, d.value)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style_of(html: &str, marker: &str) -> String {
+        let start = html.find(marker).expect("marker not found");
+        let tag_end = start + html[start..].find('>').unwrap();
+        let tag = &html[start..tag_end];
+        let Some(style) = tag.find("style=\"") else {
+            return String::new();
+        };
+        let value = &tag[style + 7..];
+        value[..value.find('"').unwrap()].to_string()
+    }
+
+    #[test]
+    fn more_specific_selector_wins() {
+        let report = inline(
+            r#"<style>p { color: red; margin: 0 } .a { color: blue } #b { color: green }</style>
+<p class="a" id="b">x</p><p class="a" data-m="2">y</p><p data-m="3">z</p>"#,
+        );
+
+        assert_eq!(
+            style_of(&report.html, "id=\"b\""),
+            "color: green; margin: 0"
+        );
+        assert_eq!(
+            style_of(&report.html, "data-m=\"2\""),
+            "color: blue; margin: 0"
+        );
+        assert_eq!(
+            style_of(&report.html, "data-m=\"3\""),
+            "color: red; margin: 0"
+        );
+        assert_eq!(report.styled_elements, 3);
+        assert!(!report.html.contains("<style"));
+    }
+
+    #[test]
+    fn later_rule_wins_at_equal_specificity() {
+        let report =
+            inline("<style>.a { color: red } .b { color: blue }</style><p class=\"a b\">x</p>");
+        assert_eq!(style_of(&report.html, "<p"), "color: blue");
+    }
+
+    #[test]
+    fn important_overrides_specificity_and_inline_style() {
+        let report = inline(
+            r#"<style>p { color: red !important } #b { color: green } em { color: red }</style>
+<p id="b">x</p><em style="color: black">y</em>"#,
+        );
+
+        assert_eq!(style_of(&report.html, "<p"), "color: red !important");
+        assert_eq!(style_of(&report.html, "<em"), "color: black");
+    }
+
+    #[test]
+    fn keeps_media_queries_and_pseudo_classes_in_style() {
+        let report = inline(
+            "<style>p { color: red } @media (max-width: 600px) { p { color: blue } } a:hover { color: green }</style><p>x</p><a>y</a>",
+        );
+
+        assert_eq!(report.kept_at_rules, 1);
+        assert_eq!(report.not_inlined, vec!["a:hover".to_string()]);
+        assert!(report.html.contains("@media (max-width: 600px)"));
+        assert!(report.html.contains("a:hover"));
+        assert_eq!(style_of(&report.html, "<p"), "color: red");
+    }
+
+    #[test]
+    fn reports_unmatched_selectors() {
+        let report = inline("<style>.missing { color: red } p { color: blue }</style><p>x</p>");
+
+        assert_eq!(report.unmatched, vec![".missing".to_string()]);
+        assert_eq!(style_of(&report.html, "<p"), "color: blue");
+    }
+
+    #[test]
+    fn keeps_rules_with_merge_placeholders() {
+        let report =
+            inline("<style>p { color: {{color}} } b { color: red }</style><p>x</p><b>y</b>");
+
+        assert!(report.html.contains("color: {{color}}"));
+        assert_eq!(style_of(&report.html, "<p"), "");
+        assert_eq!(style_of(&report.html, "<b"), "color: red");
+        assert_eq!(report.not_inlined, vec!["p (含合并字段)".to_string()]);
+    }
+}
@@ -1,5 +1,9 @@
+use batch_mail::history::CampaignRecord;
+
 use crate::views::Views;
 
 pub enum Events {
     ViewChanged(Views),
+    /// 以一条发送记录中失败和未发送的收件人为内容，在首页准备新的发送。
+    RetryCampaign(Box<CampaignRecord>),
 }
@@ -0,0 +1,318 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+
+use crate::{
+    attachments::Attachment,
+    mail_config::{MailConfig, Profiles},
+    recipients::Recipient,
+    sender::{BccBatch, Campaign, CampaignReport, RecipientResult, SendStatus},
+};
+
+/// 一次已完成的批量发送，保存在配置目录的 `history/<id>.json` 中。
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct CampaignRecord {
+    pub id: String,
+    /// 发送完成时间 (Unix 秒)。
+    pub timestamp: u64,
+    pub account: String,
+    pub subject: String,
+    pub template_path: Option<PathBuf>,
+    /// 手写的纯文本模板，见 [`Campaign::text_body`]。
+    #[serde(default)]
+    pub text_body: Option<String>,
+    #[serde(default)]
+    pub inline_css: bool,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    #[serde(default)]
+    pub personal_attachment: Option<String>,
+    #[serde(default)]
+    pub cc: String,
+    #[serde(default)]
+    pub bcc: String,
+    #[serde(default)]
+    pub reply_to: String,
+    #[serde(default)]
+    pub bcc_batch: Option<BccBatch>,
+    /// 收件人及其合并数据，与 `results` 按下标一一对应。
+    pub recipients: Vec<Recipient>,
+    pub results: Vec<RecipientResult>,
+}
+
+impl CampaignRecord {
+    pub fn new(
+        campaign: &Campaign,
+        template_path: Option<PathBuf>,
+        report: &CampaignReport,
+    ) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            id: now.as_millis().to_string(),
+            timestamp: now.as_secs(),
+            account: campaign.config.email_address.clone(),
+            subject: campaign.subject.clone(),
+            template_path,
+            text_body: campaign.text_body.clone(),
+            inline_css: campaign.inline_css,
+            attachments: campaign.attachments.clone(),
+            personal_attachment: campaign.personal_attachment.clone(),
+            cc: campaign.cc.clone(),
+            bcc: campaign.bcc.clone(),
+            reply_to: campaign.reply_to.clone(),
+            bcc_batch: campaign.bcc_batch.clone(),
+            recipients: campaign.recipients.clone(),
+            results: report.results.clone(),
+        }
+    }
+
+    pub fn history_dir() -> anyhow::Result<PathBuf> {
+        let dir = MailConfig::config_dir()?.join("history");
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        self.save_in(&Self::history_dir()?)
+    }
+
+    fn save_in(&self, dir: &Path) -> anyhow::Result<()> {
+        let path = dir.join(format!("{}.json", self.id));
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(&path, json).context("写入发送记录失败")?;
+        Ok(())
+    }
+
+    /// 读取全部发送记录，最新的在前；无法解析的文件会被忽略。
+    pub fn load_all() -> anyhow::Result<Vec<Self>> {
+        Self::load_all_from(&Self::history_dir()?)
+    }
+
+    fn load_all_from(dir: &Path) -> anyhow::Result<Vec<Self>> {
+        let mut records: Vec<Self> = fs::read_dir(dir)
+            .context("读取发送记录目录失败")?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+            .filter_map(|content| serde_json::from_str(&content).ok())
+            .collect();
+        records.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then(b.id.cmp(&a.id)));
+        Ok(records)
+    }
+
+    pub fn report(&self) -> CampaignReport {
+        CampaignReport {
+            results: self.results.clone(),
+        }
+    }
+
+    /// 需要重新发送的收件人 (连同合并数据)，见 [`SendStatus::is_retryable`]。
+    pub fn retry_recipients(&self) -> Vec<Recipient> {
+        self.recipients
+            .iter()
+            .zip(&self.results)
+            .filter(|(_, result)| result.status.is_retryable())
+            .map(|(recipient, _)| recipient.clone())
+            .collect()
+    }
+
+    /// 用于重试的账号配置名称：`current` 的发件地址与记录一致时保持不变，否则取第一个一致的；
+    /// 没有一致的账号时返回 `None`。
+    pub fn matching_profile<'a>(&self, profiles: &'a Profiles, current: &str) -> Option<&'a str> {
+        profiles
+            .profiles
+            .iter()
+            .filter(|profile| {
+                profile
+                    .config
+                    .email_address
+                    .eq_ignore_ascii_case(&self.account)
+            })
+            .min_by_key(|profile| profile.name != current)
+            .map(|profile| profile.name.as_str())
+    }
+
+    /// 需要重新发送的收件人数，与 [`CampaignRecord::retry_recipients`] 的长度相同。
+    pub fn retry_count(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| result.status.is_retryable())
+            .count()
+    }
+
+    /// 重新读取发送时使用的 HTML 模板。
+    pub fn load_template(&self) -> anyhow::Result<String> {
+        let path = self
+            .template_path
+            .as_ref()
+            .context("发送记录中没有模板路径")?;
+        fs::read_to_string(path).with_context(|| format!("读取 HTML 文件失败: {}", path.display()))
+    }
+
+    pub fn sent_count(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|r| matches!(r.status, SendStatus::Sent { .. }))
+            .count()
+    }
+
+    /// 以 UTC 显示的完成时间，如 `2025-01-31 08:30 UTC`。
+    pub fn formatted_time(&self) -> String {
+        format_timestamp(self.timestamp)
+    }
+}
+
+fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    // 公历换算，参见 Howard Hinnant 的 civil_from_days。
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::mail_config::Profile;
+
+    fn record(id: &str, timestamp: u64, statuses: Vec<SendStatus>) -> CampaignRecord {
+        let recipients: Vec<Recipient> = (0..statuses.len())
+            .map(|i| Recipient::new(format!("r{}@x.com", i)))
+            .collect();
+        let results = recipients
+            .iter()
+            .zip(statuses)
+            .map(|(recipient, status)| RecipientResult {
+                email: recipient.email.clone(),
+                status,
+                elapsed: Duration::ZERO,
+            })
+            .collect();
+        let config = MailConfig {
+            email_address: "me@x.com".to_string(),
+            ..MailConfig::default()
+        };
+        let campaign = Campaign::new(config, recipients, "Hello", "<p>Hi</p>");
+        CampaignRecord {
+            id: id.to_string(),
+            timestamp,
+            ..CampaignRecord::new(&campaign, None, &CampaignReport { results })
+        }
+    }
+
+    #[test]
+    fn retries_failed_queued_and_cancelled_recipients() {
+        let record = record(
+            "1",
+            0,
+            vec![
+                SendStatus::Sent {
+                    response: "250 OK".to_string(),
+                },
+                SendStatus::Failed {
+                    error: "421".to_string(),
+                    smtp_code: Some(421),
+                    enhanced_code: None,
+                    attempts: 3,
+                },
+                SendStatus::Skipped {
+                    reason: "地址格式错误".to_string(),
+                },
+                SendStatus::Queued,
+                SendStatus::Cancelled,
+            ],
+        );
+
+        let emails: Vec<String> = record
+            .retry_recipients()
+            .into_iter()
+            .map(|r| r.email)
+            .collect();
+        assert_eq!(emails, ["r1@x.com", "r3@x.com", "r4@x.com"]);
+        assert_eq!(record.retry_count(), 3);
+        assert_eq!(record.sent_count(), 1);
+    }
+
+    #[test]
+    fn saves_and_loads_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let older = record("100", 1_000, vec![SendStatus::Queued]);
+        let newer = record("200", 2_000, vec![SendStatus::Cancelled]);
+        older.save_in(dir.path()).unwrap();
+        newer.save_in(dir.path()).unwrap();
+        fs::write(dir.path().join("broken.json"), "{").unwrap();
+        fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let records = CampaignRecord::load_all_from(dir.path()).unwrap();
+        let ids: Vec<&str> = records.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["200", "100"]);
+        assert_eq!(records[1].account, "me@x.com");
+        assert_eq!(records[1].subject, "Hello");
+        assert_eq!(records[1].results[0].status, SendStatus::Queued);
+        assert_eq!(records[1].retry_recipients(), older.retry_recipients());
+    }
+
+    #[test]
+    fn matches_the_recorded_account() {
+        let profile = |name: &str, email: &str| Profile {
+            name: name.to_string(),
+            config: MailConfig {
+                email_address: email.to_string(),
+                ..MailConfig::default()
+            },
+        };
+        let profiles = Profiles {
+            active: "other".to_string(),
+            profiles: vec![
+                profile("other", "other@x.com"),
+                profile("work", "ME@x.com"),
+                profile("work copy", "me@x.com"),
+            ],
+        };
+        let record = record("1", 0, vec![SendStatus::Queued]);
+
+        assert_eq!(record.matching_profile(&profiles, "other"), Some("work"));
+        assert_eq!(
+            record.matching_profile(&profiles, "work copy"),
+            Some("work copy")
+        );
+
+        let unrelated = Profiles {
+            active: "other".to_string(),
+            profiles: vec![profile("other", "other@x.com")],
+        };
+        assert_eq!(record.matching_profile(&unrelated, "other"), None);
+    }
+
+    #[test]
+    fn formats_timestamps_in_utc() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_timestamp(951_827_400), "2000-02-29 12:30 UTC");
+        assert_eq!(format_timestamp(1_738_312_200), "2025-01-31 08:30 UTC");
+    }
+}
@@ -0,0 +1,588 @@
+use std::ops::Range;
+
+/// 一个开始或结束标签。标签名和属性名统一转成小写，属性值已解码实体。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub closing: bool,
+    pub self_closing: bool,
+}
+
+impl Tag {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_attr(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.attrs.iter_mut().find(|(key, _)| key == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.attrs.push((name.to_string(), value)),
+        }
+    }
+
+    /// 重新生成标签文本，属性值统一用双引号并转义。
+    pub fn to_html(&self) -> String {
+        let mut html = String::from("<");
+        if self.closing {
+            html.push('/');
+        }
+        html.push_str(&self.name);
+        for (name, value) in &self.attrs {
+            html.push(' ');
+            html.push_str(name);
+            html.push_str("=\"");
+            html.push_str(&escape(value));
+            html.push('"');
+        }
+        if self.self_closing {
+            html.push_str(" /");
+        }
+        html.push('>');
+        html
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// 标签之间的原始文本，实体尚未解码。
+    Text(&'a str),
+    Tag(Tag),
+    /// 注释、`<!DOCTYPE>` 和处理指令。
+    Other(&'a str),
+}
+
+/// 内容按原样保留、不解析其中标签的元素。
+const RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
+
+/// 把 HTML 切分成文本和标签，每个片段附带它在原文中的字节范围。
+///
+/// 只做邮件模板够用的宽松解析：不构建树，也不校验标签是否配对。
+pub fn tokenize(html: &str) -> Vec<(Range<usize>, Token<'_>)> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    let mut text_start = 0;
+
+    while let Some(offset) = html[pos..].find('<') {
+        let start = pos + offset;
+        let rest = &html[start + 1..];
+
+        let parsed = if let Some(comment) = rest.strip_prefix("!--") {
+            let end = comment
+                .find("-->")
+                .map(|i| start + 1 + 3 + i + 3)
+                .unwrap_or(html.len());
+            Some((end, Token::Other(&html[start..end])))
+        } else if rest.starts_with(['!', '?']) {
+            let end = rest
+                .find('>')
+                .map(|i| start + 1 + i + 1)
+                .unwrap_or(html.len());
+            Some((end, Token::Other(&html[start..end])))
+        } else if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '/') {
+            parse_tag(html, start).map(|(end, tag)| (end, Token::Tag(tag)))
+        } else {
+            None
+        };
+
+        let Some((end, token)) = parsed else {
+            pos = start + 1;
+            continue;
+        };
+
+        if text_start < start {
+            tokens.push((text_start..start, Token::Text(&html[text_start..start])));
+        }
+
+        let raw_text = match &token {
+            Token::Tag(tag)
+                if !tag.closing
+                    && !tag.self_closing
+                    && RAW_TEXT_ELEMENTS.contains(&tag.name.as_str()) =>
+            {
+                Some(tag.name.clone())
+            }
+            _ => None,
+        };
+        tokens.push((start..end, token));
+        pos = end;
+        text_start = end;
+
+        if let Some(name) = raw_text {
+            let close = find_ignore_case(&html[end..], &format!("</{}", name))
+                .map(|i| end + i)
+                .unwrap_or(html.len());
+            if end < close {
+                tokens.push((end..close, Token::Text(&html[end..close])));
+            }
+            pos = close;
+            text_start = close;
+        }
+    }
+
+    if text_start < html.len() {
+        tokens.push((text_start..html.len(), Token::Text(&html[text_start..])));
+    }
+    tokens
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// 解析从 `start` (指向 `<`) 开始的标签，返回标签结束后的位置。
+fn parse_tag(html: &str, start: usize) -> Option<(usize, Tag)> {
+    let bytes = html.as_bytes();
+    let mut i = start + 1;
+
+    let closing = bytes.get(i) == Some(&b'/');
+    if closing {
+        i += 1;
+    }
+
+    let name_start = i;
+    while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || matches!(bytes[i], b'-' | b':')) {
+        i += 1;
+    }
+    if i == name_start {
+        return None;
+    }
+    let name = html[name_start..i].to_ascii_lowercase();
+
+    let mut attrs = Vec::new();
+    let mut self_closing = false;
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        match bytes.get(i) {
+            None => return None,
+            Some(b'>') => {
+                i += 1;
+                break;
+            }
+            Some(b'/') => {
+                self_closing = bytes.get(i + 1) == Some(&b'>');
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let attr_start = i;
+        while i < bytes.len()
+            && !bytes[i].is_ascii_whitespace()
+            && !matches!(bytes[i], b'=' | b'>' | b'/')
+        {
+            i += 1;
+        }
+        let attr_name = html[attr_start..i].to_ascii_lowercase();
+
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let mut value = String::new();
+        if bytes.get(i) == Some(&b'=') {
+            i += 1;
+            while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            match bytes.get(i) {
+                Some(&quote @ (b'"' | b'\'')) => {
+                    let value_start = i + 1;
+                    let end = html[value_start..].find(quote as char)? + value_start;
+                    value = decode_entities(&html[value_start..end]);
+                    i = end + 1;
+                }
+                _ => {
+                    let value_start = i;
+                    while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
+                        i += 1;
+                    }
+                    value = decode_entities(&html[value_start..i]);
+                }
+            }
+        }
+        if !attr_name.is_empty() {
+            attrs.push((attr_name, value));
+        }
+    }
+
+    Some((
+        i,
+        Tag {
+            name,
+            attrs,
+            closing,
+            self_closing,
+        },
+    ))
+}
+
+/// 转义 HTML 特殊字符，结果可用于元素内容和带引号的属性值。
+pub fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// 解码常见的命名实体和数字实体，无法识别的原样保留。
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        output.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| decode_entity(&rest[1..1 + end]).map(|c| (c, end + 2)));
+        match decoded {
+            Some((c, len)) => {
+                output.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "mdash" => '—',
+        "ndash" => '–',
+        "hellip" => '…',
+        "laquo" => '«',
+        "raquo" => '»',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "middot" => '·',
+        "bull" => '•',
+        "euro" => '€',
+        "yen" => '¥',
+        "pound" => '£',
+        "times" => '×',
+        _ => return None,
+    })
+}
+
+/// 其中的内容不会出现在文本中。
+const HIDDEN_ELEMENTS: [&str; 5] = ["head", "script", "style", "title", "template"];
+
+/// 前后各留一个空行的块级元素。
+const PARAGRAPH_ELEMENTS: [&str; 9] =
+    ["p", "h1", "h2", "h3", "h4", "h5", "h6", "blockquote", "pre"];
+
+/// 前后换行的块级元素。
+const BLOCK_ELEMENTS: [&str; 16] = [
+    "div", "ul", "ol", "table", "tr", "section", "article", "header", "footer", "main", "nav",
+    "center", "address", "figure", "form", "tbody",
+];
+
+/// 累积文本输出，合并空白并处理块级元素之间的换行。
+#[derive(Default)]
+struct TextWriter {
+    output: String,
+    pending_newlines: usize,
+    pending_space: bool,
+    /// 表格单元格之间的分隔符，等到下一个单元格真正输出内容时才写入，
+    /// 这样空单元格 (常见于排版用的表格) 不会留下多余的分隔符。
+    pending_separator: bool,
+}
+
+impl TextWriter {
+    fn push_text(&mut self, text: &str) {
+        for c in text.chars() {
+            if c.is_whitespace() && c != '\u{a0}' {
+                self.pending_space = true;
+            } else {
+                self.push_char(if c == '\u{a0}' { ' ' } else { c });
+            }
+        }
+    }
+
+    /// `<pre>` 中的文本保留原有空白和换行。
+    fn push_preformatted(&mut self, text: &str) {
+        for c in text.chars() {
+            if c == '\n' {
+                self.output.push('\n');
+            } else {
+                self.push_char(c);
+            }
+        }
+    }
+
+    fn push_char(&mut self, c: char) {
+        if self.pending_newlines > 0 {
+            if !self.output.is_empty() {
+                let existing = self.output.len() - self.output.trim_end_matches('\n').len();
+                for _ in existing..self.pending_newlines {
+                    self.output.push('\n');
+                }
+            }
+            self.pending_newlines = 0;
+        } else if self.pending_separator && !self.output.is_empty() && !self.output.ends_with('\n')
+        {
+            self.output.push_str(" | ");
+        } else if self.pending_space
+            && !self.output.is_empty()
+            && !self.output.ends_with([' ', '\n'])
+        {
+            self.output.push(' ');
+        }
+        self.pending_space = false;
+        self.pending_separator = false;
+        self.output.push(c);
+    }
+
+    /// 之后的内容另起一行 (`lines` 为 2 时中间空一行)。
+    fn break_line(&mut self, lines: usize) {
+        self.pending_newlines = self.pending_newlines.max(lines);
+        self.pending_space = false;
+        self.pending_separator = false;
+    }
+
+    fn push_line_start(&mut self, prefix: &str) {
+        self.break_line(1);
+        for c in prefix.chars() {
+            self.push_char(c);
+        }
+    }
+
+    fn finish(self) -> String {
+        self.output
+            .lines()
+            .map(|line| line.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string()
+    }
+}
+
+/// 是否值得为链接加脚注：页内锚点、脚本和 `cid:` 引用对纯文本读者没有意义。
+fn is_footnote_link(href: &str) -> bool {
+    let href = href.trim();
+    !href.is_empty()
+        && !href.starts_with('#')
+        && !href.starts_with("javascript:")
+        && !href.starts_with("cid:")
+}
+
+/// 把 HTML 渲染成可读的纯文本，用作邮件的纯文本部分和预览。
+///
+/// 去掉标签和样式，保留段落、换行和列表；表格按行展开，单元格之间用 ` | ` 分隔；
+/// 链接写成 `文字 [1]`，地址统一列在末尾的脚注中。
+pub fn to_text(html: &str) -> String {
+    let mut writer = TextWriter::default();
+    let mut hidden_depth = 0usize;
+    let mut pre_depth = 0usize;
+    // 每层列表的下一个序号，无序列表为 `None`。
+    let mut lists: Vec<Option<usize>> = Vec::new();
+    // 尚未关闭的链接：开始时的输出长度和地址。
+    let mut links: Vec<(usize, String)> = Vec::new();
+    let mut footnotes: Vec<String> = Vec::new();
+
+    for (_, token) in tokenize(html) {
+        match token {
+            Token::Text(text) => {
+                if hidden_depth > 0 {
+                    continue;
+                }
+                let text = decode_entities(text);
+                if pre_depth > 0 {
+                    writer.push_preformatted(&text);
+                } else {
+                    writer.push_text(&text);
+                }
+            }
+            Token::Other(_) => {}
+            Token::Tag(tag) => {
+                let name = tag.name.as_str();
+                if HIDDEN_ELEMENTS.contains(&name) {
+                    if tag.closing {
+                        hidden_depth = hidden_depth.saturating_sub(1);
+                    } else if !tag.self_closing {
+                        hidden_depth += 1;
+                    }
+                    continue;
+                }
+                if hidden_depth > 0 {
+                    continue;
+                }
+
+                match (name, tag.closing) {
+                    ("br", _) => writer.push_line_start(""),
+                    ("hr", false) => {
+                        writer.break_line(1);
+                        writer.push_text("----------");
+                        writer.break_line(1);
+                    }
+                    ("ul", false) => {
+                        lists.push(None);
+                        writer.break_line(1);
+                    }
+                    ("ol", false) => {
+                        lists.push(Some(
+                            tag.attr("start").and_then(|s| s.parse().ok()).unwrap_or(1),
+                        ));
+                        writer.break_line(1);
+                    }
+                    ("ul" | "ol", true) => {
+                        lists.pop();
+                        writer.break_line(1);
+                    }
+                    ("li", false) => {
+                        let indent = "  ".repeat(lists.len().saturating_sub(1));
+                        let marker = match lists.last_mut() {
+                            Some(Some(n)) => {
+                                *n += 1;
+                                format!("{}{}. ", indent, *n - 1)
+                            }
+                            _ => format!("{}- ", indent),
+                        };
+                        writer.push_line_start(&marker);
+                    }
+                    ("tr", _) => writer.break_line(1),
+                    ("td" | "th", false) => writer.pending_separator = true,
+                    ("a", false) => {
+                        if let Some(href) = tag.attr("href").filter(|h| is_footnote_link(h)) {
+                            links.push((writer.output.len(), href.trim().to_string()));
+                        }
+                    }
+                    ("a", true) => {
+                        let Some((start, href)) = links.pop() else {
+                            continue;
+                        };
+                        // 链接文字本身就是地址时不再重复。
+                        let text = writer.output.get(start..).unwrap_or_default().trim();
+                        let bare = href.strip_prefix("mailto:").unwrap_or(&href);
+                        if text == href || text == bare {
+                            continue;
+                        }
+                        let number = match footnotes.iter().position(|f| *f == href) {
+                            Some(i) => i + 1,
+                            None => {
+                                footnotes.push(href);
+                                footnotes.len()
+                            }
+                        };
+                        writer.push_text(&format!(" [{}]", number));
+                    }
+                    ("img", false) => {
+                        if let Some(alt) = tag.attr("alt").map(str::trim).filter(|a| !a.is_empty())
+                        {
+                            writer.push_text(&format!(" [{}] ", alt));
+                        }
+                    }
+                    ("pre", _) => {
+                        if tag.closing {
+                            pre_depth = pre_depth.saturating_sub(1);
+                        } else {
+                            pre_depth += 1;
+                        }
+                        writer.break_line(2);
+                    }
+                    _ if PARAGRAPH_ELEMENTS.contains(&name) => writer.break_line(2),
+                    _ if BLOCK_ELEMENTS.contains(&name) => writer.break_line(1),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    if !footnotes.is_empty() {
+        writer.break_line(2);
+        for (i, href) in footnotes.iter().enumerate() {
+            writer.push_line_start(&format!("[{}] {}", i + 1, href));
+        }
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_named_and_numeric_entities() {
+        assert_eq!(
+            decode_entities("&lt;b&gt; &amp; &quot;x&quot; &apos;"),
+            "<b> & \"x\" '"
+        );
+        assert_eq!(decode_entities("&#65;&#x42;&#X43;"), "ABC");
+        assert_eq!(decode_entities("a&nbsp;b"), "a\u{a0}b");
+    }
+
+    #[test]
+    fn keeps_unknown_or_unterminated_entities() {
+        assert_eq!(decode_entities("&unknown; & &amp"), "&unknown; & &amp");
+        assert_eq!(decode_entities("&#xZZ; &#1114112;"), "&#xZZ; &#1114112;");
+        assert_eq!(decode_entities("AT&T;"), "AT&T;");
+    }
+
+    #[test]
+    fn escapes_markup_characters() {
+        assert_eq!(
+            escape(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;"
+        );
+        assert_eq!(decode_entities(&escape("<&>\"'")), "<&>\"'");
+    }
+
+    #[test]
+    fn converts_html_to_text() {
+        let text = to_text(
+            r#"<h1>Title</h1><p>Hello <b>world</b> &amp; <a href="https://x.com">site</a></p><ul><li>a</li><li>b</li></ul>"#,
+        );
+        assert_eq!(
+            text,
+            "Title\n\nHello world & site [1]\n\n- a\n- b\n\n[1] https://x.com"
+        );
+    }
+
+    #[test]
+    fn drops_style_and_script_content() {
+        let text = to_text("<style>p { color: red }</style><script>alert(1)</script><p>Hi</p>");
+        assert_eq!(text, "Hi");
+    }
+}
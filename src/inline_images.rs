@@ -0,0 +1,231 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use lettre::message::{Attachment, SinglePart, header::ContentType};
+
+use crate::html::{self, Token};
+
+/// 模板中引用的一张本地图片。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalImage {
+    /// `<img src>` 中的原始写法。
+    pub src: String,
+    /// 以模板目录为基准解析后的路径。
+    pub path: PathBuf,
+}
+
+/// 内嵌图片后的正文：`<img src>` 已改为 `cid:` 引用，`parts` 为对应的内嵌部分。
+pub struct EmbeddedImages {
+    pub html: String,
+    pub parts: Vec<SinglePart>,
+}
+
+/// 去掉地址末尾的 `?query` 和 `#fragment`，只保留文件路径部分。
+fn file_part(src: &str) -> &str {
+    let src = src.trim();
+    src.split(['?', '#']).next().unwrap_or_default()
+}
+
+/// 是否是需要嵌入的本地路径；带协议 (`http:`、`data:`、`cid:` 等) 或 `//` 开头的地址保持不变。
+fn is_local(src: &str) -> bool {
+    let src = file_part(src);
+    if src.is_empty() || src.starts_with("//") || src.contains("{{") {
+        return false;
+    }
+    // Windows 盘符 (`C:\`) 视为本地路径。
+    let is_drive =
+        src.len() > 2 && src.as_bytes()[1] == b':' && src.as_bytes()[0].is_ascii_alphabetic();
+    is_drive
+        || !src
+            .split(['/', '\\'])
+            .next()
+            .unwrap_or_default()
+            .contains(':')
+}
+
+fn resolve(src: &str, base_dir: Option<&Path>) -> PathBuf {
+    let path = PathBuf::from(file_part(src));
+    match base_dir {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path,
+    }
+}
+
+/// 列出模板中 `<img src>` 引用的本地图片，同一路径只出现一次。
+pub fn local_images(html: &str, base_dir: Option<&Path>) -> Vec<LocalImage> {
+    let mut images: Vec<LocalImage> = Vec::new();
+    for (_, token) in html::tokenize(html) {
+        let Token::Tag(tag) = token else {
+            continue;
+        };
+        if tag.name != "img" || tag.closing {
+            continue;
+        }
+        let Some(src) = tag.attr("src").filter(|src| is_local(src)) else {
+            continue;
+        };
+        if images.iter().all(|image| image.src != src) {
+            images.push(LocalImage {
+                src: src.to_string(),
+                path: resolve(src, base_dir),
+            });
+        }
+    }
+    images
+}
+
+/// 找不到文件的本地图片，这些图片发送时保持原样。
+pub fn missing_images(html: &str, base_dir: Option<&Path>) -> Vec<LocalImage> {
+    local_images(html, base_dir)
+        .into_iter()
+        .filter(|image| !image.path.is_file())
+        .collect()
+}
+
+/// 读取模板引用的本地图片，生成 `multipart/related` 的内嵌部分并把 `src` 改为 `cid:` 引用。
+/// 找不到的图片跳过，`src` 保持不变。
+pub fn embed(html: &str, base_dir: Option<&Path>) -> anyhow::Result<EmbeddedImages> {
+    let mut parts = Vec::new();
+    let mut content_ids: Vec<(String, String)> = Vec::new();
+
+    for (index, image) in local_images(html, base_dir).into_iter().enumerate() {
+        if !image.path.is_file() {
+            continue;
+        }
+        let content = fs::read(&image.path)
+            .with_context(|| format!("读取图片失败: {}", image.path.display()))?;
+        let content_type = ContentType::parse(
+            mime_guess::from_path(&image.path)
+                .first_or_octet_stream()
+                .as_ref(),
+        )
+        .unwrap_or_else(|_| ContentType::parse("application/octet-stream").unwrap());
+
+        let content_id = format!("image{}@batch-mail", index + 1);
+        let file_name = image
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| image.src.clone());
+        parts.push(
+            Attachment::new_inline_with_name(content_id.clone(), file_name)
+                .body(content, content_type),
+        );
+        content_ids.push((image.src, content_id));
+    }
+
+    if content_ids.is_empty() {
+        return Ok(EmbeddedImages {
+            html: html.to_string(),
+            parts,
+        });
+    }
+
+    let mut output = String::with_capacity(html.len());
+    let mut last = 0;
+    for (range, token) in html::tokenize(html) {
+        let Token::Tag(mut tag) = token else {
+            continue;
+        };
+        if tag.name != "img" || tag.closing {
+            continue;
+        }
+        let Some((_, content_id)) = tag
+            .attr("src")
+            .and_then(|src| content_ids.iter().find(|(s, _)| s == src))
+        else {
+            continue;
+        };
+
+        tag.set_attr("src", format!("cid:{}", content_id));
+        output.push_str(&html[last..range.start]);
+        output.push_str(&tag.to_html());
+        last = range.end;
+    }
+    output.push_str(&html[last..]);
+
+    Ok(EmbeddedImages {
+        html: output,
+        parts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_local_paths_are_embedded() {
+        for src in [
+            "logo.png",
+            "./img/a.png",
+            "/tmp/a.png",
+            "C:\\img\\a.png",
+            "a.png?v=2",
+        ] {
+            assert!(is_local(src), "{src}");
+        }
+        for src in [
+            "",
+            "https://example.com/a.png",
+            "//cdn.example.com/a.png",
+            "data:image/png;base64,AAAA",
+            "cid:image1@batch-mail",
+            "#top",
+            "?v=2",
+            "{{photo}}",
+        ] {
+            assert!(!is_local(src), "{src}");
+        }
+    }
+
+    #[test]
+    fn query_and_fragment_are_ignored_when_resolving() {
+        let dir = Path::new("/templates");
+        let images = local_images(
+            r#"<img src="logo.png?v=2"><img src="logo.png#x"><img src="logo.png?v=2">"#,
+            Some(dir),
+        );
+        assert_eq!(images.len(), 2);
+        assert!(
+            images
+                .iter()
+                .all(|image| image.path == dir.join("logo.png"))
+        );
+        assert_eq!(images[0].src, "logo.png?v=2");
+    }
+
+    #[test]
+    fn reports_missing_images() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("logo.png"), b"png").unwrap();
+
+        let html =
+            r#"<img src="logo.png?v=2"><img src="gone.png"><img src="https://example.com/a.png">"#;
+        let missing = missing_images(html, Some(dir.path()));
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].src, "gone.png");
+        assert_eq!(missing[0].path, dir.path().join("gone.png"));
+    }
+
+    #[test]
+    fn embeds_found_images_and_leaves_the_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("logo.png"), b"png").unwrap();
+
+        let html = r#"<p><img src="logo.png?v=2" alt="Logo"></p><img src="gone.png"><img src="logo.png?v=2">"#;
+        let embedded = embed(html, Some(dir.path())).unwrap();
+        assert_eq!(embedded.parts.len(), 1);
+        assert_eq!(
+            embedded.html,
+            r#"<p><img src="cid:image1@batch-mail" alt="Logo"></p><img src="gone.png"><img src="cid:image1@batch-mail">"#
+        );
+
+        let untouched = embed("<p>no images</p>", Some(dir.path())).unwrap();
+        assert!(untouched.parts.is_empty());
+        assert_eq!(untouched.html, "<p>no images</p>");
+    }
+}
@@ -0,0 +1,13 @@
+pub mod attachments;
+pub mod connection_test;
+pub mod css_inline;
+pub mod history;
+pub mod html;
+pub mod inline_images;
+pub mod mail_config;
+pub mod merge;
+pub mod rate_limit;
+pub mod recipients;
+pub mod retry;
+pub mod sender;
+pub mod vault;
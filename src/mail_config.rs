@@ -1,14 +1,108 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{Context, Ok};
 
+use crate::vault::Vault;
+
+/// 与 SMTP 服务器之间的加密方式。
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsMode {
+    /// 连接建立后立即进行 TLS 握手 (隐式 TLS，常用端口 465)。
+    Wrapper,
+    /// 明文连接后必须通过 STARTTLS 升级，服务器不支持时报错 (常用端口 587)。
+    StartTlsRequired,
+    /// 服务器支持 STARTTLS 时升级，否则继续使用明文。
+    StartTlsOpportunistic,
+    /// 全程明文，仅用于内网中继 (常用端口 25)。
+    None,
+}
+
+impl TlsMode {
+    pub const ALL: [TlsMode; 4] = [
+        TlsMode::Wrapper,
+        TlsMode::StartTlsRequired,
+        TlsMode::StartTlsOpportunistic,
+        TlsMode::None,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TlsMode::Wrapper => "SSL/TLS",
+            TlsMode::StartTlsRequired => "STARTTLS",
+            TlsMode::StartTlsOpportunistic => "STARTTLS (可选)",
+            TlsMode::None => "不加密",
+        }
+    }
+
+    pub fn default_port(self) -> u16 {
+        match self {
+            TlsMode::Wrapper => 465,
+            TlsMode::StartTlsRequired | TlsMode::StartTlsOpportunistic => 587,
+            TlsMode::None => 25,
+        }
+    }
+
+    /// 旧版配置没有 `tls_mode` 字段。当时一律使用隐式 TLS，能正常发送的配置都是隐式 TLS
+    /// (如 465、994 端口)，因此只有 587 和 25 这两个 STARTTLS 专用端口改用 STARTTLS。
+    pub fn for_legacy_port(port: u16) -> Self {
+        match port {
+            587 => TlsMode::StartTlsRequired,
+            25 => TlsMode::StartTlsOpportunistic,
+            _ => TlsMode::Wrapper,
+        }
+    }
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct MailConfig {
     pub smtp_server: String,
     pub smtp_port: u16,
+    /// 旧版配置中缺少时在读取时按端口补上，见 [`TlsMode::for_legacy_port`]。
+    pub tls_mode: TlsMode,
     pub email_address: String,
+    /// 保存在加密密码库中，不写入 `config.json`；旧版配置里的明文密码会在解锁时迁移。
+    #[serde(default, skip_serializing)]
     pub password: String,
     pub sender_name: String,
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+    /// 每秒最多发送给多少个收件人 (按信封计，含抄送、密送)，0 表示不限制。
+    #[serde(default)]
+    pub max_per_second: u32,
+    /// 每分钟最多发送给多少个收件人 (按信封计，含抄送、密送)，0 表示不限制。
+    #[serde(default)]
+    pub max_per_minute: u32,
+    /// 每天 (UTC) 最多发送给多少个收件人 (按信封计，含抄送、密送)，0 表示不限制。
+    #[serde(default)]
+    pub daily_cap: u32,
+    /// 相邻两封邮件之间的最小间隔 (毫秒)。
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// 临时错误 (4xx、连接中断) 的最大重试次数。
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// 首次重试前的等待时间 (毫秒)，之后每次翻倍。
+    #[serde(default = "default_retry_base_delay_ms")]
+    pub retry_base_delay_ms: u64,
+    /// 「发送测试」的收件人，为空时发给 `email_address`。
+    #[serde(default)]
+    pub test_addresses: Vec<String>,
+}
+
+fn default_max_concurrency() -> usize {
+    4
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_retry_base_delay_ms() -> u64 {
+    2000
 }
 
 impl Default for MailConfig {
@@ -16,43 +110,43 @@ impl Default for MailConfig {
         Self {
             smtp_server: String::new(),
             smtp_port: 587,
+            tls_mode: TlsMode::StartTlsRequired,
             email_address: String::new(),
             password: String::new(),
             sender_name: String::new(),
+            max_concurrency: default_max_concurrency(),
+            max_per_second: 0,
+            max_per_minute: 0,
+            daily_cap: 0,
+            delay_ms: 0,
+            max_retries: default_max_retries(),
+            retry_base_delay_ms: default_retry_base_delay_ms(),
+            test_addresses: Vec::new(),
         }
     }
 }
 
 impl MailConfig {
-    pub fn config_path() -> anyhow::Result<PathBuf> {
+    pub fn config_dir() -> anyhow::Result<PathBuf> {
         let config_dir = dirs::config_dir()
             .context("无法获取配置目录")?
             .join("batch_mail");
 
         fs::create_dir_all(&config_dir)?;
 
-        Ok(config_dir.join("config.json"))
+        Ok(config_dir)
     }
 
-    pub fn load() -> anyhow::Result<Self> {
-        let path = Self::config_path()?;
-
-        if !path.exists() {
-            return Ok(Self::default());
-        }
-
-        let content = fs::read_to_string(&path).context("读取配置文件失败")?;
-        let config = serde_json::from_str(&content).context("解析配置文件失败")?;
-
-        Ok(config)
+    pub fn config_path() -> anyhow::Result<PathBuf> {
+        Ok(Self::config_dir()?.join("config.json"))
     }
 
-    pub fn save(&self) -> anyhow::Result<()> {
-        let path = Self::config_path()?;
-        let json = serde_json::to_string_pretty(self).context("序列化配置失败")?;
-        fs::write(&path, json).context("写入配置文件失败")?;
-        eprintln!("配置已保存到: {:?}", path);
-        Ok(())
+    pub fn test_recipients(&self) -> Vec<String> {
+        if self.test_addresses.is_empty() {
+            vec![self.email_address.clone()]
+        } else {
+            self.test_addresses.clone()
+        }
     }
 
     pub fn validate(&self) -> anyhow::Result<()> {
@@ -71,6 +165,286 @@ impl MailConfig {
         if self.password.is_empty() {
             anyhow::bail!("密码不能为空");
         }
+        if self.max_concurrency == 0 {
+            anyhow::bail!("并发连接数至少为 1");
+        }
         Ok(())
     }
 }
+
+/// 一个命名的 SMTP 账号配置。
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct Profile {
+    pub name: String,
+    #[serde(flatten)]
+    pub config: MailConfig,
+}
+
+/// `config.json` 的内容：多个账号配置及当前选用的账号，至少包含一个账号。
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct Profiles {
+    pub active: String,
+    pub profiles: Vec<Profile>,
+}
+
+/// 兼容旧版只保存单个账号的配置文件。
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum ConfigFile {
+    Profiles(Profiles),
+    Legacy(MailConfig),
+}
+
+const DEFAULT_PROFILE_NAME: &str = "默认";
+
+/// 为缺少 `tls_mode` 的旧版账号配置按端口补上加密方式。
+fn fill_legacy_tls_mode(config: &mut serde_json::Value) {
+    let Some(object) = config.as_object_mut() else {
+        return;
+    };
+    if object.contains_key("tls_mode") {
+        return;
+    }
+    let port = object
+        .get("smtp_port")
+        .and_then(|port| port.as_u64())
+        .and_then(|port| u16::try_from(port).ok())
+        .unwrap_or(TlsMode::Wrapper.default_port());
+    let mode = serde_json::to_value(TlsMode::for_legacy_port(port)).unwrap_or_default();
+    object.insert("tls_mode".to_string(), mode);
+}
+
+/// 写入只有当前用户可读写的文件 (Unix 上为 0o600)。
+pub(crate) fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_PROFILE_NAME.to_string(),
+            profiles: vec![Profile {
+                name: DEFAULT_PROFILE_NAME.to_string(),
+                config: MailConfig::default(),
+            }],
+        }
+    }
+}
+
+impl Profiles {
+    /// 读取配置，不含密码库中的密码。
+    pub fn load() -> anyhow::Result<Self> {
+        Ok(Self::read(&MailConfig::config_path()?)?.0)
+    }
+
+    /// 读取配置文件，同时返回它是否还是旧版的单账号格式。
+    fn read(path: &Path) -> anyhow::Result<(Self, bool)> {
+        if !path.exists() {
+            return Ok((Self::default(), false));
+        }
+
+        let content = fs::read_to_string(path).context("读取配置文件失败")?;
+        let mut value: serde_json::Value =
+            serde_json::from_str(&content).context("解析配置文件失败")?;
+        match value.get_mut("profiles").and_then(|p| p.as_array_mut()) {
+            Some(profiles) => profiles.iter_mut().for_each(fill_legacy_tls_mode),
+            None => fill_legacy_tls_mode(&mut value),
+        }
+        let file: ConfigFile = serde_json::from_value(value).context("解析配置文件失败")?;
+
+        match file {
+            ConfigFile::Profiles(profiles) if !profiles.profiles.is_empty() => {
+                Ok((profiles, false))
+            }
+            ConfigFile::Profiles(_) => Ok((Self::default(), false)),
+            // 旧版文件在解锁密码库后才重写，避免明文密码还没迁移就被丢弃。
+            ConfigFile::Legacy(config) => Ok((
+                Self {
+                    active: DEFAULT_PROFILE_NAME.to_string(),
+                    profiles: vec![Profile {
+                        name: DEFAULT_PROFILE_NAME.to_string(),
+                        config,
+                    }],
+                },
+                true,
+            )),
+        }
+    }
+
+    /// 读取配置并从密码库填入各账号的密码；配置中残留的明文密码会先迁移到密码库。
+    pub fn load_unlocked(vault: &mut Vault) -> anyhow::Result<Self> {
+        Self::load_unlocked_from(&MailConfig::config_path()?, vault)
+    }
+
+    fn load_unlocked_from(path: &Path, vault: &mut Vault) -> anyhow::Result<Self> {
+        let (mut profiles, legacy) = Self::read(path)?;
+        let mut migrated = false;
+        for profile in &mut profiles.profiles {
+            if profile.config.password.is_empty() {
+                profile.config.password = vault
+                    .password(&profile.name)
+                    .unwrap_or_default()
+                    .to_string();
+            } else {
+                vault.set_password(&profile.name, &profile.config.password);
+                migrated = true;
+            }
+        }
+
+        if migrated {
+            vault.save().context("迁移明文密码失败")?;
+            eprintln!("已将明文密码迁移到加密密码库");
+        }
+        if migrated || legacy {
+            profiles.save_to(path)?;
+        }
+
+        Ok(profiles)
+    }
+
+    /// 写入 `config.json`，密码不会被序列化。
+    pub fn save(&self) -> anyhow::Result<()> {
+        self.save_to(&MailConfig::config_path()?)
+    }
+
+    fn save_to(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self).context("序列化配置失败")?;
+        write_private(path, &json).context("写入配置文件失败")?;
+        eprintln!("配置已保存到: {:?}", path);
+        Ok(())
+    }
+
+    /// 同时保存配置和各账号的密码。
+    pub fn save_with_vault(&self, vault: &mut Vault) -> anyhow::Result<()> {
+        vault.retain(&self.names());
+        for profile in &self.profiles {
+            vault.set_password(&profile.name, &profile.config.password);
+        }
+        vault.save()?;
+        self.save()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.profiles.iter().map(|p| p.name.clone()).collect()
+    }
+
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.profiles.iter().position(|p| p.name == name)
+    }
+
+    /// 当前选用账号的下标；`active` 指向不存在的账号时退回第一个。
+    pub fn active_index(&self) -> usize {
+        self.position(&self.active).unwrap_or(0)
+    }
+
+    /// 按名称取账号配置，`None` 表示当前选用的账号。
+    pub fn config(&self, name: Option<&str>) -> anyhow::Result<MailConfig> {
+        let index = match name {
+            Some(name) => self
+                .position(name)
+                .with_context(|| format!("账号配置不存在: {}", name))?,
+            None => self.active_index(),
+        };
+        self.profiles
+            .get(index)
+            .map(|p| p.config.clone())
+            .context("没有可用的账号配置")
+    }
+
+    /// 在 `base` 后追加序号，直到与现有账号名称不重复。
+    pub fn unique_name(&self, base: &str) -> String {
+        if self.position(base).is_none() {
+            return base.to_string();
+        }
+        (2..)
+            .map(|n| format!("{} {}", base, n))
+            .find(|name| self.position(name).is_none())
+            .unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut seen = std::collections::HashSet::new();
+        for profile in &self.profiles {
+            let name = profile.name.trim();
+            if name.is_empty() {
+                anyhow::bail!("账号名称不能为空");
+            }
+            if !seen.insert(name) {
+                anyhow::bail!("账号名称重复: {}", name);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_ports_default_to_implicit_tls() {
+        assert_eq!(TlsMode::for_legacy_port(465), TlsMode::Wrapper);
+        assert_eq!(TlsMode::for_legacy_port(994), TlsMode::Wrapper);
+        assert_eq!(TlsMode::for_legacy_port(2465), TlsMode::Wrapper);
+        assert_eq!(TlsMode::for_legacy_port(587), TlsMode::StartTlsRequired);
+        assert_eq!(TlsMode::for_legacy_port(25), TlsMode::StartTlsOpportunistic);
+    }
+
+    #[test]
+    fn fills_tls_mode_only_when_missing() {
+        let mut legacy = serde_json::json!({ "smtp_server": "smtp.163.com", "smtp_port": 994 });
+        fill_legacy_tls_mode(&mut legacy);
+        assert_eq!(
+            legacy["tls_mode"],
+            serde_json::to_value(TlsMode::Wrapper).unwrap()
+        );
+
+        let mut no_port = serde_json::json!({ "smtp_server": "smtp.example.com" });
+        fill_legacy_tls_mode(&mut no_port);
+        assert_eq!(
+            no_port["tls_mode"],
+            serde_json::to_value(TlsMode::Wrapper).unwrap()
+        );
+
+        let explicit = serde_json::to_value(TlsMode::None).unwrap();
+        let mut current = serde_json::json!({ "smtp_port": 994, "tls_mode": explicit.clone() });
+        fill_legacy_tls_mode(&mut current);
+        assert_eq!(current["tls_mode"], explicit);
+    }
+
+    #[test]
+    fn migrates_plaintext_passwords_into_the_vault() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.json");
+        let vault_path = dir.path().join("secrets.json");
+        fs::write(
+            &config_path,
+            r#"{"smtp_server":"smtp.163.com","smtp_port":994,"email_address":"me@163.com","password":"plain","sender_name":"Me"}"#,
+        )
+        .unwrap();
+
+        let mut vault = Vault::unlock_at(vault_path.clone(), "pw").unwrap();
+        let profiles = Profiles::load_unlocked_from(&config_path, &mut vault).unwrap();
+        assert_eq!(profiles.profiles[0].config.password, "plain");
+        assert_eq!(profiles.profiles[0].config.tls_mode, TlsMode::Wrapper);
+
+        let saved = fs::read_to_string(&config_path).unwrap();
+        assert!(saved.contains("\"profiles\""));
+        assert!(!saved.contains("plain"));
+
+        let mut vault = Vault::unlock_at(vault_path, "pw").unwrap();
+        assert_eq!(vault.password(DEFAULT_PROFILE_NAME), Some("plain"));
+        let profiles = Profiles::load_unlocked_from(&config_path, &mut vault).unwrap();
+        assert_eq!(profiles.profiles[0].config.password, "plain");
+    }
+}
@@ -5,11 +5,16 @@ use gpui_component_assets::Assets;
 
 use crate::views::app_view::AppView;
 
+mod cli;
 mod events;
-mod mail_config;
 mod views;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
     let app = Application::new().with_assets(Assets);
     app.run(move |cx| {
         gpui_component::init(cx);
@@ -0,0 +1,194 @@
+use std::collections::BTreeSet;
+
+use crate::{html, recipients::Recipient};
+
+/// 模板中一个 `{{name}}` 占位符在原文中的位置及其字段名。
+struct Placeholder<'a> {
+    start: usize,
+    end: usize,
+    name: &'a str,
+}
+
+fn scan(template: &str) -> Vec<Placeholder<'_>> {
+    let mut result = Vec::new();
+    let mut offset = 0;
+
+    while let Some(open) = template[offset..].find("{{") {
+        let start = offset + open;
+        let Some(close) = template[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + close + 2;
+        let name = template[start + 2..end - 2].trim();
+
+        if !name.is_empty() && !name.contains(['{', '}']) {
+            result.push(Placeholder { start, end, name });
+        }
+        offset = end;
+    }
+
+    result
+}
+
+pub fn placeholders(template: &str) -> BTreeSet<String> {
+    scan(template)
+        .into_iter()
+        .map(|p| p.name.to_string())
+        .collect()
+}
+
+/// 替换收件人具备的字段，缺少的占位符原样保留并一并返回缺少的字段名。
+/// `escape` 为 `true` 时按 HTML 转义字段值。
+fn substitute(template: &str, recipient: &Recipient, escape: bool) -> (String, BTreeSet<String>) {
+    let mut output = String::with_capacity(template.len());
+    let mut missing = BTreeSet::new();
+    let mut last = 0;
+
+    for placeholder in scan(template) {
+        output.push_str(&template[last..placeholder.start]);
+        match recipient.value(placeholder.name) {
+            Some(value) if escape => output.push_str(&html::escape(value)),
+            Some(value) => output.push_str(value),
+            None => {
+                output.push_str(&template[placeholder.start..placeholder.end]);
+                missing.insert(placeholder.name.to_string());
+            }
+        }
+        last = placeholder.end;
+    }
+    output.push_str(&template[last..]);
+    (output, missing)
+}
+
+fn complete(result: (String, BTreeSet<String>)) -> Result<String, BTreeSet<String>> {
+    let (output, missing) = result;
+    if missing.is_empty() {
+        Ok(output)
+    } else {
+        Err(missing)
+    }
+}
+
+/// 用收件人数据替换模板中的占位符，字段值原样插入，用于主题、纯文本和附件路径；
+/// 缺少的字段会作为错误返回。
+pub fn render(template: &str, recipient: &Recipient) -> Result<String, BTreeSet<String>> {
+    complete(substitute(template, recipient, false))
+}
+
+/// 与 [`render`] 相同，但字段值按 HTML 转义，用于 HTML 正文。
+pub fn render_html(template: &str, recipient: &Recipient) -> Result<String, BTreeSet<String>> {
+    complete(substitute(template, recipient, true))
+}
+
+/// 用于预览：替换收件人具备的字段，缺少的占位符原样保留。
+pub fn render_partial(template: &str, recipient: &Recipient) -> String {
+    substitute(template, recipient, false).0
+}
+
+/// 与 [`render_partial`] 相同，但字段值按 HTML 转义。
+pub fn render_partial_html(template: &str, recipient: &Recipient) -> String {
+    substitute(template, recipient, true).0
+}
+
+/// 检查每个收件人是否具备模板所需的全部字段，返回缺失字段的收件人列表。
+pub fn find_missing<'a>(
+    templates: &[&str],
+    recipients: impl IntoIterator<Item = &'a Recipient>,
+) -> Vec<(&'a Recipient, BTreeSet<String>)> {
+    let required: BTreeSet<String> = templates.iter().flat_map(|t| placeholders(t)).collect();
+
+    recipients
+        .into_iter()
+        .filter_map(|recipient| {
+            let missing: BTreeSet<String> = required
+                .iter()
+                .filter(|name| recipient.value(name).is_none())
+                .cloned()
+                .collect();
+            (!missing.is_empty()).then_some((recipient, missing))
+        })
+        .collect()
+}
+
+pub fn describe_missing(missing: &[(&Recipient, BTreeSet<String>)]) -> String {
+    missing
+        .iter()
+        .map(|(recipient, fields)| {
+            let fields: Vec<&str> = fields.iter().map(|f| f.as_str()).collect();
+            format!("{}: 缺少 {}", recipient.email, fields.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipient(email: &str, fields: &[(&str, &str)]) -> Recipient {
+        let mut recipient = Recipient::new(email);
+        for (key, value) in fields {
+            recipient.fields.insert(key.to_string(), value.to_string());
+        }
+        recipient
+    }
+
+    #[test]
+    fn collects_placeholder_names() {
+        let names = placeholders("{{ name }}, {{company}} {{name}} {{}} {{a{b}}");
+        assert_eq!(
+            names,
+            BTreeSet::from(["company".to_string(), "name".to_string()])
+        );
+    }
+
+    #[test]
+    fn renders_fields_and_email() {
+        let alice = recipient("alice@example.com", &[("name", "Alice")]);
+        assert_eq!(
+            render("Hi {{ name }} <{{email}}>", &alice).unwrap(),
+            "Hi Alice <alice@example.com>"
+        );
+        assert_eq!(render("no fields", &alice).unwrap(), "no fields");
+    }
+
+    #[test]
+    fn reports_missing_fields() {
+        let alice = recipient("alice@example.com", &[("name", "Alice")]);
+        let missing = render("{{name}} {{company}} {{city}}", &alice).unwrap_err();
+        assert_eq!(
+            missing,
+            BTreeSet::from(["city".to_string(), "company".to_string()])
+        );
+        assert_eq!(
+            render_partial("{{name}} {{company}}", &alice),
+            "Alice {{company}}"
+        );
+    }
+
+    #[test]
+    fn escapes_values_only_in_html() {
+        let bob = recipient("bob@example.com", &[("name", "<b>Tom & Jerry</b>")]);
+        assert_eq!(render("{{name}}", &bob).unwrap(), "<b>Tom & Jerry</b>");
+        assert_eq!(
+            render_html("<p>{{name}}</p>", &bob).unwrap(),
+            "<p>&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;</p>"
+        );
+    }
+
+    #[test]
+    fn finds_recipients_missing_fields() {
+        let alice = recipient(
+            "alice@example.com",
+            &[("name", "Alice"), ("company", "ACME")],
+        );
+        let bob = recipient("bob@example.com", &[("name", "Bob")]);
+        let recipients = [alice, bob];
+
+        let missing = find_missing(&["Hi {{name}}", "<p>{{company}}</p>"], &recipients);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].0.email, "bob@example.com");
+        assert_eq!(missing[0].1, BTreeSet::from(["company".to_string()]));
+        assert_eq!(describe_missing(&missing), "bob@example.com: 缺少 company");
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+
+use crate::mail_config::MailConfig;
+
+struct LimiterState {
+    recent: VecDeque<Instant>,
+    last: Option<Instant>,
+}
+
+/// 按每秒/每分钟上限和最小间隔节流发送，多个并发投递共享同一个实例。
+pub struct RateLimiter {
+    per_second: usize,
+    per_minute: usize,
+    delay: Duration,
+    state: tokio::sync::Mutex<LimiterState>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &MailConfig) -> Self {
+        Self {
+            per_second: config.max_per_second as usize,
+            per_minute: config.max_per_minute as usize,
+            delay: Duration::from_millis(config.delay_ms),
+            state: tokio::sync::Mutex::new(LimiterState {
+                recent: VecDeque::new(),
+                last: None,
+            }),
+        }
+    }
+
+    /// 等待直到可以再发送一封有 `count` 个收件人的邮件，并按收件人数记录这次发送。
+    ///
+    /// 密送群发的每批人数在发送前已限制在上限以内；单封邮件因抄送、密送使收件人数超过
+    /// 某个时间窗口的上限时，等到该窗口内没有其他发送记录再发送，避免永远等待。
+    pub async fn acquire(&self, count: usize) {
+        let mut state = self.state.lock().await;
+
+        loop {
+            let now = Instant::now();
+            while state
+                .recent
+                .front()
+                .is_some_and(|t| now.duration_since(*t) >= Duration::from_secs(60))
+            {
+                state.recent.pop_front();
+            }
+
+            let mut ready_at = now;
+            if let Some(last) = state.last {
+                ready_at = ready_at.max(last + self.delay);
+            }
+            for (limit, window) in [
+                (self.per_second, Duration::from_secs(1)),
+                (self.per_minute, Duration::from_secs(60)),
+            ] {
+                if limit == 0 {
+                    continue;
+                }
+                let in_window: Vec<&Instant> = state
+                    .recent
+                    .iter()
+                    .filter(|t| now.duration_since(**t) < window)
+                    .collect();
+                let needed = count.clamp(1, limit);
+                if in_window.len() + needed > limit {
+                    let expire = in_window.len() + needed - limit;
+                    ready_at = ready_at.max(*in_window[expire - 1] + window);
+                }
+            }
+
+            if ready_at <= now {
+                state.recent.extend(std::iter::repeat_n(now, count.max(1)));
+                state.last = Some(now);
+                return;
+            }
+            tokio::time::sleep_until(ready_at.into()).await;
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default, Clone, Copy)]
+struct QuotaUsage {
+    day: u64,
+    sent: u32,
+}
+
+/// 按账号记录当天 (UTC) 已发送数量，持久化在配置目录的 `quota.json` 中。
+pub struct DailyQuota {
+    account: String,
+    cap: u32,
+    path: Option<PathBuf>,
+    usage: Mutex<QuotaUsage>,
+}
+
+fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / 86_400
+}
+
+impl DailyQuota {
+    pub fn quota_path() -> anyhow::Result<PathBuf> {
+        Ok(MailConfig::config_dir()?.join("quota.json"))
+    }
+
+    pub fn load(config: &MailConfig) -> anyhow::Result<Self> {
+        let account = config.email_address.clone();
+        if config.daily_cap == 0 {
+            return Ok(Self {
+                account,
+                cap: 0,
+                path: None,
+                usage: Mutex::default(),
+            });
+        }
+
+        let path = Self::quota_path()?;
+        let mut all = Self::read_all(&path)?;
+        let usage = all
+            .remove(&account)
+            .filter(|usage| usage.day == today())
+            .unwrap_or(QuotaUsage {
+                day: today(),
+                sent: 0,
+            });
+
+        Ok(Self {
+            account,
+            cap: config.daily_cap,
+            path: Some(path),
+            usage: Mutex::new(usage),
+        })
+    }
+
+    fn read_all(path: &Path) -> anyhow::Result<BTreeMap<String, QuotaUsage>> {
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(path).context("读取配额文件失败")?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    fn persist(&self, usage: QuotaUsage) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = Self::read_all(path).and_then(|mut all| {
+            all.insert(self.account.clone(), usage);
+            let json = serde_json::to_string_pretty(&all)?;
+            fs::write(path, json).context("写入配额文件失败")
+        });
+        if let Err(e) = result {
+            eprintln!("保存配额失败: {:?}", e);
+        }
+    }
+
+    pub fn remaining(&self) -> Option<u32> {
+        if self.cap == 0 {
+            return None;
+        }
+        let usage = self.usage.lock().unwrap();
+        let sent = if usage.day == today() { usage.sent } else { 0 };
+        Some(self.cap.saturating_sub(sent))
+    }
+
+    /// 为 `count` 个收件人预占配额；当天剩余配额不足时不预占并返回 `false`。
+    pub fn try_reserve(&self, count: u32) -> bool {
+        if self.cap == 0 {
+            return true;
+        }
+        let mut usage = self.usage.lock().unwrap();
+        if usage.day != today() {
+            *usage = QuotaUsage {
+                day: today(),
+                sent: 0,
+            };
+        }
+        if usage.sent.saturating_add(count) > self.cap {
+            return false;
+        }
+        usage.sent += count;
+        self.persist(*usage);
+        true
+    }
+
+    /// 归还未能成功投递的预占配额。
+    pub fn release(&self, count: u32) {
+        if self.cap == 0 {
+            return;
+        }
+        let mut usage = self.usage.lock().unwrap();
+        usage.sent = usage.sent.saturating_sub(count);
+        self.persist(*usage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(per_second: usize) -> RateLimiter {
+        RateLimiter {
+            per_second,
+            per_minute: 0,
+            delay: Duration::ZERO,
+            state: tokio::sync::Mutex::new(LimiterState {
+                recent: VecDeque::new(),
+                last: None,
+            }),
+        }
+    }
+
+    fn quota(cap: u32) -> DailyQuota {
+        DailyQuota {
+            account: "me@example.com".to_string(),
+            cap,
+            path: None,
+            usage: Mutex::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn limiter_counts_recipients() {
+        let limiter = limiter(2);
+        let start = Instant::now();
+
+        limiter.acquire(2).await;
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        limiter.acquire(1).await;
+        assert!(start.elapsed() >= Duration::from_millis(900));
+    }
+
+    #[tokio::test]
+    async fn limiter_sends_an_oversized_message_only_through_an_empty_window() {
+        let limiter = limiter(2);
+        let start = Instant::now();
+
+        limiter.acquire(1).await;
+        limiter.acquire(5).await;
+        assert!(start.elapsed() >= Duration::from_millis(900));
+    }
+
+    #[test]
+    fn quota_reserves_whole_batches() {
+        let quota = quota(3);
+
+        assert!(quota.try_reserve(2));
+        assert!(!quota.try_reserve(2));
+        assert_eq!(quota.remaining(), Some(1));
+
+        quota.release(2);
+        assert!(quota.try_reserve(3));
+        assert_eq!(quota.remaining(), Some(0));
+    }
+
+    #[test]
+    fn zero_cap_is_unlimited() {
+        let quota = quota(0);
+        assert!(quota.try_reserve(1000));
+        assert_eq!(quota.remaining(), None);
+    }
+}
@@ -0,0 +1,622 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fs,
+    path::Path,
+};
+
+use anyhow::Context;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Recipient {
+    pub email: String,
+    pub fields: BTreeMap<String, String>,
+    /// 收件人在输入文本或导入文件中的行号，未知时为 0。
+    pub line: u64,
+}
+
+impl Recipient {
+    pub fn new(email: impl Into<String>) -> Self {
+        Self {
+            email: email.into(),
+            fields: BTreeMap::new(),
+            line: 0,
+        }
+    }
+
+    pub fn value(&self, key: &str) -> Option<&str> {
+        if key == "email" {
+            return Some(&self.email);
+        }
+        self.fields
+            .get(key)
+            .map(|v| v.as_str())
+            .filter(|v| !v.trim().is_empty())
+    }
+
+    /// 收件人数据中覆盖 `field` 全局设置的地址列表，列名不区分大小写，没有该列或为空时返回 `None`。
+    pub fn copy_override(&self, field: CopyField) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(name, _)| {
+                field
+                    .columns()
+                    .contains(&name.trim().to_lowercase().as_str())
+            })
+            .map(|(_, value)| value.as_str())
+            .filter(|value| !value.trim().is_empty())
+    }
+
+    /// 检查各个覆盖列中的地址，返回第一个问题的说明。
+    fn invalid_copy(&self) -> Option<String> {
+        CopyField::ALL.into_iter().find_map(|field| {
+            let error = parse_addresses(self.copy_override(field)?).err()?;
+            Some(format!("{}地址无效: {}", field.label(), error))
+        })
+    }
+}
+
+/// 抄送、密送和回复地址，可以在收件人数据中用同名列按人覆盖。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyField {
+    Cc,
+    Bcc,
+    ReplyTo,
+}
+
+impl CopyField {
+    pub const ALL: [CopyField; 3] = [CopyField::Cc, CopyField::Bcc, CopyField::ReplyTo];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CopyField::Cc => "抄送",
+            CopyField::Bcc => "密送",
+            CopyField::ReplyTo => "回复",
+        }
+    }
+
+    /// 可用于覆盖的列名 (小写)。
+    pub fn columns(self) -> &'static [&'static str] {
+        match self {
+            CopyField::Cc => &["cc", "抄送"],
+            CopyField::Bcc => &["bcc", "密送"],
+            CopyField::ReplyTo => &["reply_to", "reply-to", "回复地址"],
+        }
+    }
+}
+
+/// 解析以逗号、分号或换行分隔的地址列表，忽略空项；有无效地址时返回第一个的说明。
+pub fn parse_addresses(text: &str) -> Result<Vec<lettre::message::Mailbox>, String> {
+    text.split([',', ';', '\n'])
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .map(|address| {
+            address
+                .parse::<lettre::message::Mailbox>()
+                .map_err(|e| format!("{} ({})", address, e))
+        })
+        .collect()
+}
+
+fn is_email_column(name: &str) -> bool {
+    matches!(
+        name.trim().to_lowercase().as_str(),
+        "email" | "e-mail" | "mail" | "邮箱" | "邮箱地址"
+    )
+}
+
+/// 解析收件人输入框内容。
+///
+/// 每行一个邮箱地址 (可带显示名，如 `"Doe, J" <j@x.com>`)；如果第一行不含 `@`，
+/// 则视为表头 (如 `email,name,company`)，按导入文件的同一套表格解析 (支持带引号的 CSV 字段)，
+/// 邮箱列按列名识别，否则取包含 `@` 最多的列。
+pub fn parse_recipients(text: &str) -> (Vec<Recipient>, Vec<RejectedRow>) {
+    let Some(first) = text.lines().map(str::trim).find(|l| !l.is_empty()) else {
+        return (Vec::new(), Vec::new());
+    };
+
+    if !first.contains('@') {
+        return match RecipientTable::parse(text, sniff_delimiter(None, text)) {
+            Ok(table) => table.to_recipients(&table.default_mapping()),
+            Err(e) => (
+                Vec::new(),
+                vec![RejectedRow {
+                    line: 0,
+                    reason: format!("{:#}", e),
+                }],
+            ),
+        };
+    }
+
+    let mut recipients = Vec::new();
+    let mut rejected = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, email) in text.lines().map(str::trim).enumerate() {
+        let line = i as u64 + 1;
+        if email.is_empty() {
+            continue;
+        }
+        if !seen.insert(email.to_lowercase()) {
+            rejected.push(RejectedRow {
+                line,
+                reason: format!("重复的邮箱地址: {}", email),
+            });
+            continue;
+        }
+        recipients.push(Recipient {
+            line,
+            ..Recipient::new(email)
+        });
+    }
+    (recipients, rejected)
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidRecipient {
+    /// 在收件人列表中的下标。
+    pub index: usize,
+    pub line: u64,
+    pub email: String,
+    pub reason: String,
+}
+
+/// 检查收件人地址及其抄送类覆盖地址能否作为 SMTP 收件人使用，返回全部无效项。
+pub fn find_invalid(recipients: &[Recipient]) -> Vec<InvalidRecipient> {
+    recipients
+        .iter()
+        .enumerate()
+        .filter_map(|(index, recipient)| {
+            let reason = if recipient.email.trim().is_empty() {
+                "邮箱地址为空".to_string()
+            } else if let Err(e) = recipient.email.parse::<lettre::message::Mailbox>() {
+                e.to_string()
+            } else {
+                recipient.invalid_copy()?
+            };
+            Some(InvalidRecipient {
+                index,
+                line: recipient.line,
+                email: recipient.email.clone(),
+                reason,
+            })
+        })
+        .collect()
+}
+
+pub fn describe_invalid(invalid: &[InvalidRecipient]) -> String {
+    invalid
+        .iter()
+        .map(|item| format!("第 {} 行: {} ({})", item.line, item.email, item.reason))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[derive(Debug, Clone)]
+struct TableRow {
+    line: u64,
+    cells: Vec<String>,
+}
+
+/// 从 CSV/TSV 文件导入的原始表格，列映射确定后再转换为收件人列表。
+#[derive(Debug, Clone)]
+pub struct RecipientTable {
+    pub headers: Vec<String>,
+    pub has_header: bool,
+    rows: Vec<TableRow>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMapping {
+    pub email_column: usize,
+    pub variable_columns: BTreeSet<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RejectedRow {
+    pub line: u64,
+    pub reason: String,
+}
+
+fn sniff_delimiter(path: Option<&Path>, content: &str) -> u8 {
+    let extension = path
+        .and_then(|p| p.extension())
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    if matches!(extension.as_deref(), Some("tsv" | "tab")) {
+        return b'\t';
+    }
+
+    let first_line = content.lines().next().unwrap_or_default();
+    [b'\t', b';', b',']
+        .into_iter()
+        .max_by_key(|d| first_line.matches(*d as char).count())
+        .filter(|d| first_line.contains(*d as char))
+        .unwrap_or(b',')
+}
+
+/// 记录起始位置所在的行号。csv 读取器给出的位置可能落在记录前被跳过的空行上，
+/// 这里跳过这些空行再计数。
+fn line_at(content: &str, byte: usize) -> u64 {
+    let rest = &content[byte.min(content.len())..];
+    let skipped = rest.len() - rest.trim_start_matches(['\r', '\n']).len();
+    content[..byte.min(content.len()) + skipped]
+        .matches('\n')
+        .count() as u64
+        + 1
+}
+
+impl RecipientTable {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path).context("读取收件人文件失败 (需为 UTF-8 编码)")?;
+        let content = content.trim_start_matches('\u{feff}');
+        Self::parse(content, sniff_delimiter(Some(path), content))
+    }
+
+    pub fn parse(content: &str, delimiter: u8) -> anyhow::Result<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(content.as_bytes());
+
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record.context("解析收件人文件失败")?;
+            if record.iter().all(|cell| cell.trim().is_empty()) {
+                continue;
+            }
+            rows.push(TableRow {
+                line: record
+                    .position()
+                    .map(|p| line_at(content, p.byte() as usize))
+                    .unwrap_or_default(),
+                cells: record.iter().map(|cell| cell.trim().to_string()).collect(),
+            });
+        }
+
+        if rows.is_empty() {
+            anyhow::bail!("收件人文件为空");
+        }
+
+        let has_header = !rows[0].cells.iter().any(|cell| cell.contains('@'));
+        let headers = if has_header {
+            rows.remove(0).cells
+        } else {
+            let width = rows.iter().map(|r| r.cells.len()).max().unwrap_or_default();
+            (1..=width).map(|i| format!("列{}", i)).collect()
+        };
+
+        Ok(Self {
+            headers,
+            has_header,
+            rows,
+        })
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn sample(&self, column: usize) -> Option<&str> {
+        self.rows
+            .iter()
+            .filter_map(|row| row.cells.get(column))
+            .find(|cell| !cell.is_empty())
+            .map(|cell| cell.as_str())
+    }
+
+    /// 邮箱列优先按表头名识别，其次取包含 `@` 最多的列；其余列默认作为合并变量。
+    pub fn default_mapping(&self) -> ColumnMapping {
+        let email_column = self
+            .headers
+            .iter()
+            .position(|h| self.has_header && is_email_column(h))
+            .unwrap_or_else(|| {
+                (0..self.headers.len())
+                    .max_by_key(|&i| {
+                        self.rows
+                            .iter()
+                            .filter(|r| r.cells.get(i).is_some_and(|c| c.contains('@')))
+                            .count()
+                    })
+                    .unwrap_or(0)
+            });
+
+        ColumnMapping {
+            email_column,
+            variable_columns: (0..self.headers.len())
+                .filter(|&i| i != email_column)
+                .collect(),
+        }
+    }
+
+    /// 按列映射转换为收件人列表。只拒绝列数与表头不符和重复的行；地址为空或无效的行照常返回，
+    /// 由 [`find_invalid`] 和发送时的「跳过无效地址」统一处理。
+    pub fn to_recipients(&self, mapping: &ColumnMapping) -> (Vec<Recipient>, Vec<RejectedRow>) {
+        let mut recipients = Vec::new();
+        let mut rejected = Vec::new();
+        let mut seen = HashSet::new();
+
+        for row in &self.rows {
+            let reject = |reason: String| RejectedRow {
+                line: row.line,
+                reason,
+            };
+
+            if self.has_header && row.cells.len() != self.headers.len() {
+                rejected.push(reject(format!(
+                    "列数为 {}，应为 {}",
+                    row.cells.len(),
+                    self.headers.len()
+                )));
+                continue;
+            }
+
+            // 没有表头时列数取最宽的行，较短的行缺少的单元格按空值处理。
+            let cell = |i: usize| row.cells.get(i).map(String::as_str).unwrap_or_default();
+            let email = cell(mapping.email_column);
+            if !email.is_empty() && !seen.insert(email.to_lowercase()) {
+                rejected.push(reject(format!("重复的邮箱地址: {}", email)));
+                continue;
+            }
+
+            let fields = mapping
+                .variable_columns
+                .iter()
+                .filter(|&&i| i != mapping.email_column)
+                .map(|&i| (self.headers[i].clone(), cell(i).to_string()))
+                .collect();
+
+            recipients.push(Recipient {
+                email: email.to_string(),
+                fields,
+                line: row.line,
+            });
+        }
+
+        (recipients, rejected)
+    }
+}
+
+/// 从文件加载收件人：`.csv`/`.tsv` 按表格导入并使用默认列映射，其他文件按每行一个地址解析。
+pub fn load_recipients_file(path: &Path) -> anyhow::Result<(Vec<Recipient>, Vec<RejectedRow>)> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+
+    if matches!(extension.as_deref(), Some("csv" | "tsv" | "tab")) {
+        let table = RecipientTable::load(path)?;
+        Ok(table.to_recipients(&table.default_mapping()))
+    } else {
+        let content = fs::read_to_string(path).context("读取收件人文件失败")?;
+        Ok(parse_recipients(&content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emails(recipients: &[Recipient]) -> Vec<&str> {
+        recipients.iter().map(|r| r.email.as_str()).collect()
+    }
+
+    #[test]
+    fn typed_list_without_header_is_one_address_per_line() {
+        let (recipients, rejected) = parse_recipients("a@x.com\nb@x.com, Bob\n\nc@x.com\n");
+
+        assert_eq!(emails(&recipients), ["a@x.com", "b@x.com, Bob", "c@x.com"]);
+        assert_eq!(recipients[2].line, 4);
+        assert!(rejected.is_empty());
+    }
+
+    #[test]
+    fn typed_list_keeps_quoted_display_names() {
+        let (recipients, rejected) = parse_recipients("\"Doe, J\" <j@x.com>\nk@x.com");
+
+        assert_eq!(emails(&recipients), ["\"Doe, J\" <j@x.com>", "k@x.com"]);
+        assert!(rejected.is_empty());
+        assert!(find_invalid(&recipients).is_empty());
+    }
+
+    #[test]
+    fn typed_list_rejects_duplicates() {
+        let (recipients, rejected) = parse_recipients("a@x.com\nA@x.com");
+
+        assert_eq!(emails(&recipients), ["a@x.com"]);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].line, 2);
+    }
+
+    #[test]
+    fn typed_list_with_header_splits_fields() {
+        let (recipients, rejected) =
+            parse_recipients("name,email\n\"Doe, J\",j@x.com\nAnn,ann@x.com\nBad\n");
+
+        assert_eq!(emails(&recipients), ["j@x.com", "ann@x.com"]);
+        assert_eq!(recipients[0].value("name"), Some("Doe, J"));
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].line, 4);
+    }
+
+    #[test]
+    fn headerless_table_pads_short_rows() {
+        let table = RecipientTable::parse("a@x.com\nb@x.com,Bob\nc@x.com\n", b',').unwrap();
+        let (recipients, rejected) = table.to_recipients(&table.default_mapping());
+
+        assert!(!table.has_header);
+        assert_eq!(table.headers, ["列1", "列2"]);
+        assert_eq!(emails(&recipients), ["a@x.com", "b@x.com", "c@x.com"]);
+        assert_eq!(
+            recipients[0].fields.get("列2").map(String::as_str),
+            Some("")
+        );
+        assert_eq!(recipients[1].value("列2"), Some("Bob"));
+        assert!(rejected.is_empty());
+    }
+
+    #[test]
+    fn sniffs_delimiter_from_extension_and_first_line() {
+        assert_eq!(sniff_delimiter(Some(Path::new("list.TSV")), "a,b"), b'\t');
+        assert_eq!(sniff_delimiter(None, "name\temail\nA\ta@x.com"), b'\t');
+        assert_eq!(sniff_delimiter(None, "name;email;note,x"), b';');
+        assert_eq!(sniff_delimiter(None, "name,email"), b',');
+        assert_eq!(sniff_delimiter(None, "email"), b',');
+    }
+
+    #[test]
+    fn detects_header_and_email_column_by_name() {
+        let table =
+            RecipientTable::parse("Name,E-Mail,Note\nAnn,ann@x.com,see a@b.c\n", b',').unwrap();
+
+        assert!(table.has_header);
+        assert_eq!(table.headers, ["Name", "E-Mail", "Note"]);
+        assert_eq!(table.row_count(), 1);
+        assert_eq!(
+            table.default_mapping(),
+            ColumnMapping {
+                email_column: 1,
+                variable_columns: BTreeSet::from([0, 2]),
+            }
+        );
+    }
+
+    #[test]
+    fn falls_back_to_column_with_most_addresses() {
+        let table = RecipientTable::parse(
+            "who,address\nAnn,ann@x.com\nBob,bob@x.com\nCc me@x.com,\n",
+            b',',
+        )
+        .unwrap();
+
+        assert_eq!(table.default_mapping().email_column, 1);
+        assert_eq!(table.sample(0), Some("Ann"));
+    }
+
+    #[test]
+    fn imports_tsv_with_quoted_fields_and_line_numbers() {
+        let content =
+            "email\tname\tnote\n\nann@x.com\t\"Doe\tAnn\"\t\"said \"\"hi\"\"\"\nbob@x.com\tBob\t\n";
+        let table = RecipientTable::parse(content, sniff_delimiter(None, content)).unwrap();
+        let (recipients, rejected) = table.to_recipients(&table.default_mapping());
+
+        assert!(rejected.is_empty());
+        assert_eq!(emails(&recipients), ["ann@x.com", "bob@x.com"]);
+        assert_eq!(recipients[0].value("name"), Some("Doe\tAnn"));
+        assert_eq!(recipients[0].value("note"), Some("said \"hi\""));
+        assert_eq!(recipients[0].line, 3);
+        assert_eq!(recipients[1].line, 4);
+        assert_eq!(recipients[1].value("note"), None);
+    }
+
+    #[test]
+    fn headerless_file_uses_numbered_columns() {
+        let table = RecipientTable::parse("Ann,ann@x.com\nBob,bob@x.com\n", b',').unwrap();
+        let mapping = table.default_mapping();
+        let (recipients, _) = table.to_recipients(&mapping);
+
+        assert!(!table.has_header);
+        assert_eq!(table.row_count(), 2);
+        assert_eq!(mapping.email_column, 1);
+        assert_eq!(recipients[0].value("列1"), Some("Ann"));
+    }
+
+    #[test]
+    fn header_rows_reject_wrong_widths_and_duplicates() {
+        let table = RecipientTable::parse(
+            "email,name\nann@x.com,Ann\nbob@x.com\nANN@x.com,Again\n,Blank\n",
+            b',',
+        )
+        .unwrap();
+        let (recipients, rejected) = table.to_recipients(&table.default_mapping());
+
+        assert_eq!(emails(&recipients), ["ann@x.com", ""]);
+        let reasons: Vec<(u64, &str)> = rejected
+            .iter()
+            .map(|r| (r.line, r.reason.as_str()))
+            .collect();
+        assert_eq!(
+            reasons,
+            [(3, "列数为 1，应为 2"), (4, "重复的邮箱地址: ANN@x.com")]
+        );
+    }
+
+    #[test]
+    fn rejects_empty_tables() {
+        assert!(RecipientTable::parse("\n\n , \n", b',').is_err());
+    }
+
+    #[test]
+    fn loads_files_by_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let csv = dir.path().join("list.csv");
+        fs::write(&csv, "\u{feff}email,name\nann@x.com,Ann\n").unwrap();
+        let txt = dir.path().join("list.txt");
+        fs::write(&txt, "ann@x.com\n\"Doe, J\" <j@x.com>\n").unwrap();
+
+        let (from_csv, _) = load_recipients_file(&csv).unwrap();
+        let (from_txt, _) = load_recipients_file(&txt).unwrap();
+
+        assert_eq!(from_csv[0].value("name"), Some("Ann"));
+        assert_eq!(emails(&from_txt), ["ann@x.com", "\"Doe, J\" <j@x.com>"]);
+    }
+
+    #[test]
+    fn reports_invalid_addresses_with_line_numbers() {
+        let (recipients, _) =
+            parse_recipients("email,name\nann@x.com,Ann\n\nnot-an-address,Bob\n,Empty\n");
+        let invalid = find_invalid(&recipients);
+
+        let found: Vec<(usize, u64, &str)> = invalid
+            .iter()
+            .map(|item| (item.index, item.line, item.email.as_str()))
+            .collect();
+        assert_eq!(found, [(1, 4, "not-an-address"), (2, 5, "")]);
+        assert_eq!(invalid[1].reason, "邮箱地址为空");
+
+        let description = describe_invalid(&invalid);
+        assert!(description.starts_with("第 4 行: not-an-address ("));
+        assert!(description.contains("\n第 5 行:  (邮箱地址为空)"));
+    }
+
+    #[test]
+    fn parses_address_lists() {
+        let mailboxes = parse_addresses(" a@x.com; \"Doe\" <b@x.com>,\n\n c@x.com ,").unwrap();
+        let addresses: Vec<String> = mailboxes.iter().map(|m| m.email.to_string()).collect();
+        assert_eq!(addresses, ["a@x.com", "b@x.com", "c@x.com"]);
+        assert_eq!(mailboxes[1].name.as_deref(), Some("Doe"));
+
+        assert!(parse_addresses("").unwrap().is_empty());
+        let error = parse_addresses("a@x.com, nope").unwrap_err();
+        assert!(error.starts_with("nope ("));
+    }
+
+    #[test]
+    fn copy_overrides_match_columns_case_insensitively() {
+        let (recipients, _) = parse_recipients(
+            "email,CC,Reply-To,密送\nann@x.com,boss@x.com; hr@x.com,,\nbob@x.com,,r@x.com,\n",
+        );
+
+        assert_eq!(
+            recipients[0].copy_override(CopyField::Cc),
+            Some("boss@x.com; hr@x.com")
+        );
+        assert_eq!(recipients[0].copy_override(CopyField::ReplyTo), None);
+        assert_eq!(
+            recipients[1].copy_override(CopyField::ReplyTo),
+            Some("r@x.com")
+        );
+        assert_eq!(recipients[1].copy_override(CopyField::Bcc), None);
+        assert!(find_invalid(&recipients).is_empty());
+    }
+
+    #[test]
+    fn invalid_copy_override_marks_the_recipient_invalid() {
+        let (recipients, _) = parse_recipients("email,bcc\nann@x.com,oops\nbob@x.com,b@x.com\n");
+        let invalid = find_invalid(&recipients);
+
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].line, 2);
+        assert!(invalid[0].reason.starts_with("密送地址无效: oops ("));
+    }
+}
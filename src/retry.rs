@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use lettre::transport::smtp;
+
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// 对一次 SMTP 投递失败的分类结果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmtpFailure {
+    pub message: String,
+    /// 服务器返回的三位状态码，如 `421`、`550`；连接层错误没有状态码。
+    pub code: Option<u16>,
+    /// RFC 3463 增强状态码，如 `4.7.0`、`5.1.1`。
+    pub enhanced_code: Option<String>,
+    /// 4xx 响应以及网络/连接错误视为临时错误，可以重试。
+    pub transient: bool,
+}
+
+impl SmtpFailure {
+    pub fn classify(error: &smtp::Error) -> Self {
+        let message = error.to_string();
+        let code = error.status().and_then(|c| c.to_string().parse().ok());
+        let transient = if error.is_transient() {
+            true
+        } else if error.is_permanent() {
+            false
+        } else {
+            error.is_timeout() || !(error.is_tls() || error.is_client() || error.is_response())
+        };
+
+        Self {
+            enhanced_code: extract_enhanced_code(&message),
+            message,
+            code,
+            transient,
+        }
+    }
+}
+
+/// 从响应文本中找出形如 `x.y.z` 的增强状态码 (x 为 2、4 或 5)。
+pub fn extract_enhanced_code(text: &str) -> Option<String> {
+    text.split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | ':' | ',' | ';' | '['))
+        .map(|token| token.trim_end_matches('.'))
+        .find(|token| {
+            let parts: Vec<&str> = token.split('.').collect();
+            parts.len() == 3
+                && matches!(parts[0], "2" | "4" | "5")
+                && parts[1..]
+                    .iter()
+                    .all(|p| (1..=3).contains(&p.len()) && p.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(|token| token.to_string())
+}
+
+/// 第 `attempt` 次重试前的等待时间：`base * 2^(attempt - 1)`，最长 5 分钟。
+pub fn backoff(attempt: u32, base: Duration) -> Duration {
+    base.saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_enhanced_status_codes() {
+        assert_eq!(
+            extract_enhanced_code("550 5.1.1 <bob@example.com>: Recipient address rejected")
+                .as_deref(),
+            Some("5.1.1")
+        );
+        assert_eq!(
+            extract_enhanced_code("421 4.7.0 Try again later, closing connection.").as_deref(),
+            Some("4.7.0")
+        );
+        assert_eq!(
+            extract_enhanced_code("permanent error (554): 5.7.1 Message rejected.").as_deref(),
+            Some("5.7.1")
+        );
+        assert_eq!(
+            extract_enhanced_code("rejected (5.7.26)").as_deref(),
+            Some("5.7.26")
+        );
+    }
+
+    #[test]
+    fn ignores_text_without_enhanced_codes() {
+        assert_eq!(extract_enhanced_code("250 OK"), None);
+        assert_eq!(extract_enhanced_code("server 1.2.3 at 10.0.0.1"), None);
+        assert_eq!(extract_enhanced_code("5.1.1234 5.a.1"), None);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_limit() {
+        let base = Duration::from_secs(2);
+        assert_eq!(backoff(0, base), Duration::from_secs(2));
+        assert_eq!(backoff(1, base), Duration::from_secs(2));
+        assert_eq!(backoff(2, base), Duration::from_secs(4));
+        assert_eq!(backoff(4, base), Duration::from_secs(16));
+        assert_eq!(backoff(9, base), MAX_BACKOFF);
+        assert_eq!(backoff(u32::MAX, base), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,1448 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use futures::{StreamExt, stream};
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    address::Envelope,
+    message::{Mailbox, MultiPart, SinglePart},
+    transport::smtp::{
+        AsyncSmtpTransportBuilder, PoolConfig,
+        authentication::Credentials,
+        client::{Tls, TlsParameters},
+    },
+};
+use tokio::{runtime::Runtime, sync::watch};
+
+use crate::{
+    attachments::{self, Attachment},
+    css_inline, html, inline_images,
+    mail_config::{MailConfig, TlsMode},
+    merge,
+    rate_limit::{DailyQuota, RateLimiter},
+    recipients::{self, CopyField, Recipient},
+    retry::{self, SmtpFailure},
+};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum SendStatus {
+    Sent {
+        response: String,
+    },
+    Failed {
+        error: String,
+        /// 最后一次失败的 SMTP 状态码。
+        smtp_code: Option<u16>,
+        /// 最后一次失败的增强状态码，如 `5.1.1`。
+        enhanced_code: Option<String>,
+        /// 实际尝试投递的次数，未连接服务器就失败时为 0。
+        attempts: u32,
+    },
+    /// 地址无效，未尝试投递。
+    Skipped {
+        reason: String,
+    },
+    /// 当天配额已用完，留待之后发送。
+    Queued,
+    Cancelled,
+}
+
+impl SendStatus {
+    fn failed(error: impl Into<String>) -> Self {
+        SendStatus::Failed {
+            error: error.into(),
+            smtp_code: None,
+            enhanced_code: None,
+            attempts: 0,
+        }
+    }
+
+    /// 失败或从未尝试投递 (排队、取消) 的收件人可以重新发送；无效地址除外。
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            SendStatus::Failed { .. } | SendStatus::Queued | SendStatus::Cancelled
+        )
+    }
+
+    /// 供结果表和日志显示的一行说明。
+    pub fn detail(&self) -> String {
+        match self {
+            SendStatus::Sent { response } => response.clone(),
+            SendStatus::Failed {
+                error,
+                smtp_code,
+                enhanced_code,
+                attempts,
+            } => {
+                let codes: Vec<String> = smtp_code
+                    .map(|c| c.to_string())
+                    .into_iter()
+                    .chain(enhanced_code.clone())
+                    .collect();
+                let mut detail = if codes.is_empty() {
+                    error.clone()
+                } else {
+                    format!("[{}] {}", codes.join(" "), error)
+                };
+                if *attempts > 1 {
+                    detail.push_str(&format!(" (共尝试 {} 次)", attempts));
+                }
+                detail
+            }
+            SendStatus::Skipped { reason } => reason.clone(),
+            SendStatus::Queued => "已达每日上限".to_string(),
+            SendStatus::Cancelled => "未发送".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunState {
+    Running,
+    Paused,
+    Cancelled,
+}
+
+/// 运行中发送任务的控制句柄，可在任意线程中暂停、继续或取消。
+///
+/// 发送循环只在两封邮件之间检查状态，正在投递的那一封不会被中断；
+/// 等待频率限制或重试退避时取消会立即生效。
+#[derive(Debug, Clone)]
+pub struct SendControl {
+    state: Arc<watch::Sender<RunState>>,
+}
+
+impl Default for SendControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SendControl {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(watch::Sender::new(RunState::Running)),
+        }
+    }
+
+    pub fn state(&self) -> RunState {
+        *self.state.borrow()
+    }
+
+    pub fn pause(&self) {
+        self.state.send_if_modified(|state| {
+            let changed = *state == RunState::Running;
+            if changed {
+                *state = RunState::Paused;
+            }
+            changed
+        });
+    }
+
+    pub fn resume(&self) {
+        self.state.send_if_modified(|state| {
+            let changed = *state == RunState::Paused;
+            if changed {
+                *state = RunState::Running;
+            }
+            changed
+        });
+    }
+
+    pub fn cancel(&self) {
+        self.state.send_replace(RunState::Cancelled);
+    }
+
+    /// 暂停时等待恢复；返回 `false` 表示任务已被取消。
+    async fn checkpoint(&self) -> bool {
+        let mut rx = self.state.subscribe();
+        loop {
+            match *rx.borrow_and_update() {
+                RunState::Running => return true,
+                RunState::Cancelled => return false,
+                RunState::Paused => {}
+            }
+            if rx.changed().await.is_err() {
+                return true;
+            }
+        }
+    }
+
+    /// 在任务被取消前等待 `future` 完成；期间被取消时返回 `None`。
+    async fn unless_cancelled<F: Future>(&self, future: F) -> Option<F::Output> {
+        let mut rx = self.state.subscribe();
+        tokio::select! {
+            output = future => Some(output),
+            Ok(_) = rx.wait_for(|state| *state == RunState::Cancelled) => None,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct RecipientResult {
+    pub email: String,
+    pub status: SendStatus,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CampaignReport {
+    pub results: Vec<RecipientResult>,
+}
+
+impl CampaignReport {
+    pub fn sent_count(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|r| matches!(r.status, SendStatus::Sent { .. }))
+            .count()
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &RecipientResult> {
+        self.results
+            .iter()
+            .filter(|r| matches!(r.status, SendStatus::Failed { .. }))
+    }
+
+    pub fn failed_count(&self) -> usize {
+        self.failed().count()
+    }
+
+    pub fn skipped(&self) -> impl Iterator<Item = (&RecipientResult, &str)> {
+        self.results.iter().filter_map(|r| match &r.status {
+            SendStatus::Skipped { reason } => Some((r, reason.as_str())),
+            _ => None,
+        })
+    }
+
+    pub fn skipped_count(&self) -> usize {
+        self.skipped().count()
+    }
+
+    pub fn queued(&self) -> impl Iterator<Item = &RecipientResult> {
+        self.results
+            .iter()
+            .filter(|r| r.status == SendStatus::Queued)
+    }
+
+    pub fn queued_count(&self) -> usize {
+        self.queued().count()
+    }
+
+    pub fn cancelled(&self) -> impl Iterator<Item = &RecipientResult> {
+        self.results
+            .iter()
+            .filter(|r| r.status == SendStatus::Cancelled)
+    }
+
+    pub fn cancelled_count(&self) -> usize {
+        self.cancelled().count()
+    }
+
+    pub fn all_failed(&self) -> bool {
+        self.failed_count() > 0 && self.sent_count() == 0
+    }
+
+    pub fn summary(&self) -> String {
+        let failed_list = self
+            .failed()
+            .map(|r| format!("{}: {}", r.email, r.status.detail()))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut summary = if self.failed_count() == 0 {
+            format!("成功发送 {} 封邮件", self.sent_count())
+        } else if self.sent_count() > 0 {
+            format!(
+                "部分成功: {} 封成功, {} 封失败\n失败列表:\n{}",
+                self.sent_count(),
+                self.failed_count(),
+                failed_list
+            )
+        } else {
+            format!("全部失败:\n{}", failed_list)
+        };
+
+        if self.skipped_count() > 0 {
+            let skipped_list = self
+                .skipped()
+                .map(|(r, reason)| format!("{}: {}", r.email, reason))
+                .collect::<Vec<_>>()
+                .join("\n");
+            summary.push_str(&format!(
+                "\n已跳过 {} 个无效地址:\n{}",
+                self.skipped_count(),
+                skipped_list
+            ));
+        }
+
+        if self.queued_count() > 0 {
+            let queued_list = self
+                .queued()
+                .map(|r| r.email.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            summary.push_str(&format!(
+                "\n已达每日发送上限, {} 个收件人已排队, 配额恢复后可在发送记录中重试:\n{}",
+                self.queued_count(),
+                queued_list
+            ));
+        }
+
+        if self.cancelled_count() > 0 {
+            let cancelled_list = self
+                .cancelled()
+                .map(|r| r.email.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            summary.push_str(&format!(
+                "\n已取消, {} 个收件人未发送:\n{}",
+                self.cancelled_count(),
+                cancelled_list
+            ));
+        }
+
+        summary
+    }
+}
+
+struct DeliveryContext<'a> {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+    from_mailbox: Mailbox,
+    /// 图片已改为 `cid:` 引用的正文模板。
+    html_body: String,
+    /// 发送前一次性读取好的内嵌图片和附件，每封邮件共用。
+    images: Vec<SinglePart>,
+    attachments: Vec<SinglePart>,
+    control: &'a SendControl,
+    limiter: RateLimiter,
+    quota: DailyQuota,
+}
+
+/// 密送群发：每 `size` 个收件人合并为一封邮件，收件人全部放在密送中，
+/// 信头的 To 只显示 `visible_to` (留空时显示发件人自己)，该地址本身不会收到邮件。
+///
+/// 整批收件人收到的是同一封邮件，因此模板中不能使用合并字段，也不能按人设置附件或抄送；
+/// 全局抄送和密送会随每一批重复发送，同样不能使用。
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BccBatch {
+    pub size: usize,
+    pub visible_to: String,
+}
+
+/// 一次批量发送任务：同一份主题和正文模板，按收件人数据逐一合并后发送。
+#[derive(Debug, Clone)]
+pub struct Campaign {
+    pub config: MailConfig,
+    pub recipients: Vec<Recipient>,
+    pub subject: String,
+    pub html_body: String,
+    /// 手写的纯文本模板；未设置时由 HTML 自动转换生成纯文本部分。
+    pub text_body: Option<String>,
+    pub attachments: Vec<Attachment>,
+    /// 每个收件人各自的附件路径模板，可以使用合并字段，如 `invoices/{{id}}.pdf`
+    /// 或直接取一列数据 `{{attachment}}`。
+    pub personal_attachment: Option<String>,
+    /// 模板所在目录，相对路径以此为基准；未设置时相对于当前工作目录。
+    pub base_dir: Option<PathBuf>,
+    /// 全局的抄送、密送和回复地址，多个地址以逗号或分号分隔；
+    /// 收件人数据中有对应列 (见 [`CopyField::columns`]) 且非空时按人覆盖。
+    pub cc: String,
+    pub bcc: String,
+    pub reply_to: String,
+    /// 设置后改为密送群发，见 [`BccBatch`]。
+    pub bcc_batch: Option<BccBatch>,
+    /// 发送前把 `<style>` 中的 CSS 内联到元素的 `style` 属性上，见 [`css_inline::inline`]。
+    pub inline_css: bool,
+    /// 为 `true` 时跳过无效地址继续发送，否则存在无效地址时拒绝开始。
+    pub skip_invalid: bool,
+    /// 测试发送的实际收件地址，见 [`Campaign::test_copy`]。非空时邮件只发给这些地址，
+    /// 不抄送也不密送，合并字段仍取收件人数据。
+    pub test_to: Vec<String>,
+}
+
+impl Campaign {
+    pub fn new(
+        config: MailConfig,
+        recipients: Vec<Recipient>,
+        subject: impl Into<String>,
+        html_body: impl Into<String>,
+    ) -> Self {
+        Self {
+            config,
+            recipients,
+            subject: subject.into(),
+            html_body: html_body.into(),
+            text_body: None,
+            attachments: Vec::new(),
+            personal_attachment: None,
+            base_dir: None,
+            cc: String::new(),
+            bcc: String::new(),
+            reply_to: String::new(),
+            bcc_batch: None,
+            inline_css: false,
+            skip_invalid: false,
+            test_to: Vec::new(),
+        }
+    }
+
+    /// 设置手写的纯文本模板，空白模板视为不使用。
+    pub fn text_body(mut self, text_body: Option<String>) -> Self {
+        self.text_body = text_body.filter(|t| !t.trim().is_empty());
+        self
+    }
+
+    /// 设置个性化附件的路径模板，空白模板视为不使用。
+    pub fn personal_attachment(mut self, pattern: Option<String>) -> Self {
+        self.personal_attachment = pattern.filter(|p| !p.trim().is_empty());
+        self
+    }
+
+    pub fn base_dir(mut self, base_dir: Option<PathBuf>) -> Self {
+        self.base_dir = base_dir;
+        self
+    }
+
+    pub fn attachments(mut self, attachments: Vec<Attachment>) -> Self {
+        self.attachments = attachments;
+        self
+    }
+
+    pub fn cc(mut self, cc: impl Into<String>) -> Self {
+        self.cc = cc.into();
+        self
+    }
+
+    pub fn bcc(mut self, bcc: impl Into<String>) -> Self {
+        self.bcc = bcc.into();
+        self
+    }
+
+    pub fn reply_to(mut self, reply_to: impl Into<String>) -> Self {
+        self.reply_to = reply_to.into();
+        self
+    }
+
+    pub fn bcc_batch(mut self, bcc_batch: Option<BccBatch>) -> Self {
+        self.bcc_batch = bcc_batch;
+        self
+    }
+
+    fn copy_setting(&self, field: CopyField) -> &str {
+        match field {
+            CopyField::Cc => &self.cc,
+            CopyField::Bcc => &self.bcc,
+            CopyField::ReplyTo => &self.reply_to,
+        }
+    }
+
+    /// 某个收件人实际使用的抄送类地址：数据中的覆盖列优先，否则使用全局设置。
+    fn copy_addresses(
+        &self,
+        recipient: &Recipient,
+        field: CopyField,
+    ) -> Result<Vec<Mailbox>, String> {
+        let text = recipient
+            .copy_override(field)
+            .unwrap_or(self.copy_setting(field));
+        recipients::parse_addresses(text).map_err(|e| format!("{}地址无效: {}", field.label(), e))
+    }
+
+    pub fn inline_css(mut self, inline_css: bool) -> Self {
+        self.inline_css = inline_css;
+        self
+    }
+
+    pub fn skip_invalid(mut self, skip_invalid: bool) -> Self {
+        self.skip_invalid = skip_invalid;
+        self
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.recipients.is_empty() {
+            anyhow::bail!("没有有效的收件人地址");
+        }
+        if self.subject.trim().is_empty() {
+            anyhow::bail!("邮件主题不能为空");
+        }
+        self.config
+            .validate()
+            .map_err(|e| anyhow::anyhow!("配置验证失败: {}", e))?;
+        for field in CopyField::ALL {
+            recipients::parse_addresses(self.copy_setting(field))
+                .map_err(|e| anyhow::anyhow!("{}地址无效: {}", field.label(), e))?;
+        }
+        for address in &self.test_to {
+            address
+                .parse::<Mailbox>()
+                .map_err(|e| anyhow::anyhow!("测试收件人地址无效: {} ({})", address, e))?;
+        }
+
+        let invalid = recipients::find_invalid(&self.recipients);
+        if !invalid.is_empty() && !self.skip_invalid {
+            anyhow::bail!(
+                "以下 {} 个收件人地址无效:\n{}",
+                invalid.len(),
+                recipients::describe_invalid(&invalid)
+            );
+        }
+        if invalid.len() == self.recipients.len() {
+            anyhow::bail!("没有有效的收件人地址");
+        }
+
+        let mut templates = vec![self.subject.as_str(), self.html_body.as_str()];
+        templates.extend(self.text_body.as_deref());
+        if let Some(batch) = &self.bcc_batch {
+            self.check_bcc_batch(batch, &templates)?;
+        }
+        let missing = merge::find_missing(&templates, self.valid_recipients());
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "以下收件人缺少合并字段:\n{}",
+                merge::describe_missing(&missing)
+            );
+        }
+
+        self.check_attachments()?;
+        Ok(())
+    }
+
+    /// 检查每个 (地址有效的) 收件人的个性化附件：路径所需的合并字段齐全且文件存在。
+    /// 返回检查过的收件人数，未设置个性化附件时为 0。
+    pub fn check_attachments(&self) -> anyhow::Result<usize> {
+        let Some(pattern) = &self.personal_attachment else {
+            return Ok(0);
+        };
+        let recipients = self.valid_recipients();
+
+        let missing = merge::find_missing(&[pattern], recipients.iter().copied());
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "以下收件人缺少附件路径所需的合并字段:\n{}",
+                merge::describe_missing(&missing)
+            );
+        }
+
+        let missing: Vec<(&Recipient, PathBuf)> = recipients
+            .iter()
+            .filter_map(|recipient| {
+                let path = attachments::personal_path(pattern, recipient, self.base_dir.as_deref())
+                    .ok()
+                    .flatten()?;
+                (!path.is_file()).then_some((*recipient, path))
+            })
+            .collect();
+        if !missing.is_empty() {
+            let list: Vec<String> = missing
+                .iter()
+                .map(|(recipient, path)| format!("{}: {}", recipient.email, path.display()))
+                .collect();
+            anyhow::bail!(
+                "以下 {} 个收件人的附件不存在:\n{}",
+                missing.len(),
+                list.join("\n")
+            );
+        }
+        Ok(recipients.len())
+    }
+
+    fn check_bcc_batch(&self, batch: &BccBatch, templates: &[&str]) -> anyhow::Result<()> {
+        if batch.size == 0 {
+            anyhow::bail!("密送群发的每批人数必须大于 0");
+        }
+        // 一批的全部收件人同时计入配额和频率限制，超过任一上限的批次永远无法在限制内发出。
+        for (label, limit) in [
+            ("每秒", self.config.max_per_second),
+            ("每分钟", self.config.max_per_minute),
+            ("每日", self.config.daily_cap),
+        ] {
+            if limit > 0 && batch.size > limit as usize {
+                anyhow::bail!(
+                    "密送群发的每批人数 ({}) 不能超过{}发送上限 ({})",
+                    batch.size,
+                    label,
+                    limit
+                );
+            }
+        }
+        if !batch.visible_to.trim().is_empty() {
+            batch
+                .visible_to
+                .trim()
+                .parse::<Mailbox>()
+                .map_err(|e| anyhow::anyhow!("群发显示的收件人地址无效: {}", e))?;
+        }
+
+        let fields: BTreeSet<String> = templates
+            .iter()
+            .flat_map(|template| merge::placeholders(template))
+            .collect();
+        if !fields.is_empty() {
+            let fields: Vec<&str> = fields.iter().map(|f| f.as_str()).collect();
+            anyhow::bail!(
+                "密送群发时整批收件人收到同一封邮件，主题和正文中不能使用合并字段: {}",
+                fields.join(", ")
+            );
+        }
+        if self.personal_attachment.is_some() {
+            anyhow::bail!("密送群发时不能使用个性化附件");
+        }
+        if !self.cc.trim().is_empty() || !self.bcc.trim().is_empty() {
+            anyhow::bail!(
+                "密送群发时每一批都会抄送，不能设置全局抄送或密送；如需抄送，请把这些地址加入收件人列表"
+            );
+        }
+
+        let overridden: Vec<String> = self
+            .valid_recipients()
+            .into_iter()
+            .filter(|r| CopyField::ALL.iter().any(|&f| r.copy_override(f).is_some()))
+            .map(|r| format!("第 {} 行: {}", r.line, r.email))
+            .collect();
+        if !overridden.is_empty() {
+            anyhow::bail!(
+                "密送群发时不能按收件人设置抄送、密送或回复地址，以下收件人数据中有对应列:\n{}",
+                overridden.join("\n")
+            );
+        }
+        Ok(())
+    }
+
+    fn valid_recipients(&self) -> Vec<&Recipient> {
+        let invalid: HashSet<usize> = recipients::find_invalid(&self.recipients)
+            .iter()
+            .map(|i| i.index)
+            .collect();
+        self.recipients
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !invalid.contains(i))
+            .map(|(_, r)| r)
+            .collect()
+    }
+
+    /// 生成一份测试发送：只保留第一个 (地址有效的) 收件人，主题加上 `[TEST]` 前缀，
+    /// 只发给 `config.test_addresses` (未设置时发给发件账号自己)。
+    ///
+    /// 与正式发送走同一套逐人合并、内嵌图片和附件流程，收到的内容与该收件人的一致；
+    /// 不抄送也不密送，也不使用密送群发，回复地址沿用该收件人实际使用的设置。
+    pub fn test_copy(&self) -> anyhow::Result<Campaign> {
+        let test_to = self.config.test_recipients();
+        let sample = match self.valid_recipients().first() {
+            Some(recipient) => (*recipient).clone(),
+            None => match self.recipients.first() {
+                Some(recipient) => recipient.clone(),
+                None => Recipient::new(test_to.first().cloned().unwrap_or_default()),
+            },
+        };
+
+        Ok(Campaign {
+            recipients: vec![sample],
+            subject: format!("[TEST] {}", self.subject),
+            bcc_batch: None,
+            skip_invalid: false,
+            test_to,
+            ..self.clone()
+        })
+    }
+
+    pub async fn send(&self) -> anyhow::Result<CampaignReport> {
+        self.send_with_progress(&SendControl::new(), |_, _| {})
+            .await
+    }
+
+    /// 与 [`Campaign::send`] 相同，但每处理完一个收件人就以 `(序号, 结果)` 回调一次，
+    /// 并在每封邮件之间响应 `control` 的暂停与取消。
+    ///
+    /// 最多同时投递 `config.max_concurrency` 封邮件，回调按完成顺序触发，
+    /// 返回的报告按收件人原顺序排列。需要在 tokio 运行时中调用，见 [`runtime`]。
+    pub async fn send_with_progress(
+        &self,
+        control: &SendControl,
+        mut on_result: impl FnMut(usize, &RecipientResult),
+    ) -> anyhow::Result<CampaignReport> {
+        self.validate()?;
+
+        let config = &self.config;
+        let from_mailbox: Mailbox = format!("{} <{}>", config.sender_name, config.email_address)
+            .parse()
+            .map_err(|e| anyhow::anyhow!("发件人地址格式错误: {}", e))?;
+
+        let attachments = self
+            .attachments
+            .iter()
+            .map(Attachment::to_part)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let creds = Credentials::new(config.email_address.clone(), config.password.clone());
+        let concurrency = config.max_concurrency.max(1);
+
+        let mailer = transport(config)?
+            .credentials(creds)
+            .pool_config(PoolConfig::new().max_size(concurrency as u32))
+            .build();
+
+        let html_body = if self.inline_css {
+            css_inline::inline(&self.html_body).html
+        } else {
+            self.html_body.clone()
+        };
+        let embedded = inline_images::embed(&html_body, self.base_dir.as_deref())?;
+
+        let context = DeliveryContext {
+            mailer,
+            from_mailbox,
+            html_body: embedded.html,
+            images: embedded.parts,
+            attachments,
+            control,
+            limiter: RateLimiter::new(config),
+            quota: DailyQuota::load(config)?,
+        };
+
+        let mut results = Vec::with_capacity(self.recipients.len());
+        let groups: Vec<Vec<usize>> = match &self.bcc_batch {
+            None => (0..self.recipients.len())
+                .map(|index| vec![index])
+                .collect(),
+            Some(batch) => {
+                let invalid = recipients::find_invalid(&self.recipients);
+                for item in &invalid {
+                    let result = RecipientResult {
+                        email: item.email.clone(),
+                        status: SendStatus::Skipped {
+                            reason: format!("地址格式错误: {}", item.reason),
+                        },
+                        elapsed: Duration::ZERO,
+                    };
+                    on_result(item.index, &result);
+                    results.push((item.index, result));
+                }
+                let invalid: HashSet<usize> = invalid.iter().map(|item| item.index).collect();
+                let valid: Vec<usize> = (0..self.recipients.len())
+                    .filter(|index| !invalid.contains(index))
+                    .collect();
+                valid
+                    .chunks(batch.size)
+                    .map(|chunk| chunk.to_vec())
+                    .collect()
+            }
+        };
+
+        let mut deliveries = stream::iter(groups)
+            .map(|group| self.deliver_group(group, &context))
+            .buffer_unordered(concurrency);
+
+        while let Some(group) = deliveries.next().await {
+            for (index, result) in group {
+                on_result(index, &result);
+                results.push((index, result));
+            }
+        }
+        results.sort_by_key(|(index, _)| *index);
+
+        Ok(CampaignReport {
+            results: results.into_iter().map(|(_, result)| result).collect(),
+        })
+    }
+
+    /// 投递一组收件人：普通模式下每组只有一个收件人，密送群发时一组即一封邮件。
+    async fn deliver_group(
+        &self,
+        group: Vec<usize>,
+        context: &DeliveryContext<'_>,
+    ) -> Vec<(usize, RecipientResult)> {
+        match &self.bcc_batch {
+            Some(batch) => self.deliver_batch(&group, batch, context).await,
+            None => vec![self.deliver(group[0], context).await],
+        }
+    }
+
+    /// 把一批收件人放进密送投递同一封邮件，整批共用投递结果。
+    async fn deliver_batch(
+        &self,
+        indices: &[usize],
+        batch: &BccBatch,
+        context: &DeliveryContext<'_>,
+    ) -> Vec<(usize, RecipientResult)> {
+        let results = |status: SendStatus, elapsed| {
+            indices
+                .iter()
+                .map(|&index| {
+                    let result = RecipientResult {
+                        email: self.recipients[index].email.clone(),
+                        status: status.clone(),
+                        elapsed,
+                    };
+                    (index, result)
+                })
+                .collect()
+        };
+
+        if !context.control.checkpoint().await {
+            return results(SendStatus::Cancelled, Duration::ZERO);
+        }
+
+        let email = match self.batch_message(indices, batch, context) {
+            Ok(email) => email,
+            Err(e) => {
+                let error = format!("构建邮件失败: {:#}", e);
+                return results(SendStatus::failed(error), Duration::ZERO);
+            }
+        };
+
+        let (status, elapsed) = self.transmit(&email, context).await;
+        results(status, elapsed)
+    }
+
+    /// 构建一批收件人共用的邮件。信封只包含这一批密送的收件人，
+    /// 信头 To 中显示的地址不会收到邮件。
+    fn batch_message(
+        &self,
+        indices: &[usize],
+        batch: &BccBatch,
+        context: &DeliveryContext<'_>,
+    ) -> anyhow::Result<Message> {
+        let visible_to = match batch.visible_to.trim() {
+            "" => context.from_mailbox.clone(),
+            address => address.parse()?,
+        };
+        let mut builder = Message::builder()
+            .from(context.from_mailbox.clone())
+            .to(visible_to)
+            .subject(self.subject.clone());
+
+        let mut envelope_to = Vec::new();
+        for &index in indices {
+            let mailbox: Mailbox = self.recipients[index].email.parse()?;
+            envelope_to.push(mailbox.email.clone());
+            builder = builder.bcc(mailbox);
+        }
+        for mailbox in recipients::parse_addresses(&self.reply_to)
+            .map_err(|e| anyhow::anyhow!("回复地址无效: {}", e))?
+        {
+            builder = builder.reply_to(mailbox);
+        }
+
+        let envelope = Envelope::new(Some(context.from_mailbox.email.clone()), envelope_to)?;
+        let text = self
+            .text_body
+            .clone()
+            .unwrap_or_else(|| html::to_text(&context.html_body));
+        let body = message_body(
+            context.html_body.clone(),
+            text,
+            &context.images,
+            context.attachments.clone(),
+        );
+        Ok(builder.envelope(envelope).multipart(body)?)
+    }
+
+    async fn deliver(
+        &self,
+        index: usize,
+        context: &DeliveryContext<'_>,
+    ) -> (usize, RecipientResult) {
+        let recipient = &self.recipients[index];
+        let result = |status, elapsed| {
+            let email = if self.test_to.is_empty() {
+                recipient.email.clone()
+            } else {
+                self.test_to.join(", ")
+            };
+            let result = RecipientResult {
+                email,
+                status,
+                elapsed,
+            };
+            (index, result)
+        };
+
+        if !context.control.checkpoint().await {
+            return result(SendStatus::Cancelled, Duration::ZERO);
+        }
+
+        let to = if self.test_to.is_empty() {
+            vec![recipient.email.as_str()]
+        } else {
+            self.test_to.iter().map(String::as_str).collect()
+        };
+        let to = match to
+            .into_iter()
+            .map(str::parse)
+            .collect::<Result<Vec<Mailbox>, _>>()
+        {
+            Ok(to) => to,
+            Err(e) => {
+                let reason = format!("地址格式错误: {}", e);
+                return result(SendStatus::Skipped { reason }, Duration::ZERO);
+            }
+        };
+
+        let copies = CopyField::ALL.map(|field| self.copy_addresses(recipient, field));
+        let [mut cc, mut bcc, reply_to] = match copies {
+            [Ok(cc), Ok(bcc), Ok(reply_to)] => [cc, bcc, reply_to],
+            [Err(reason), ..] | [_, Err(reason), _] | [.., Err(reason)] => {
+                return result(SendStatus::Skipped { reason }, Duration::ZERO);
+            }
+        };
+        if !self.test_to.is_empty() {
+            cc.clear();
+            bcc.clear();
+        }
+
+        let text = match &self.text_body {
+            Some(template) => merge::render(template, recipient).map(Some),
+            None => Ok(None),
+        };
+        let (subject, body, text) = match (
+            merge::render(&self.subject, recipient),
+            merge::render_html(&context.html_body, recipient),
+            text,
+        ) {
+            (Ok(subject), Ok(body), Ok(text)) => {
+                let text = text.unwrap_or_else(|| html::to_text(&body));
+                (subject, body, text)
+            }
+            _ => {
+                return result(SendStatus::failed("合并字段缺失"), Duration::ZERO);
+            }
+        };
+
+        let mut parts = context.attachments.clone();
+        if let Some(pattern) = &self.personal_attachment {
+            let part =
+                match attachments::personal_path(pattern, recipient, self.base_dir.as_deref()) {
+                    Ok(Some(path)) => Attachment::load(&path).and_then(|a| a.to_part()).map(Some),
+                    Ok(None) => Ok(None),
+                    Err(_) => Err(anyhow::anyhow!("合并字段缺失")),
+                };
+            match part {
+                Ok(part) => parts.extend(part),
+                Err(e) => {
+                    return result(SendStatus::failed(format!("{:#}", e)), Duration::ZERO);
+                }
+            }
+        }
+
+        let mut builder = Message::builder()
+            .from(context.from_mailbox.clone())
+            .subject(subject);
+        for mailbox in to {
+            builder = builder.to(mailbox);
+        }
+        for mailbox in cc {
+            builder = builder.cc(mailbox);
+        }
+        for mailbox in bcc {
+            builder = builder.bcc(mailbox);
+        }
+        for mailbox in reply_to {
+            builder = builder.reply_to(mailbox);
+        }
+        let email = match builder.multipart(message_body(body, text, &context.images, parts)) {
+            Ok(email) => email,
+            Err(e) => {
+                let error = format!("构建邮件失败: {}", e);
+                return result(SendStatus::failed(error), Duration::ZERO);
+            }
+        };
+
+        let (status, elapsed) = self.transmit(&email, context).await;
+        result(status, elapsed)
+    }
+
+    /// 预占配额后投递一封已构建好的邮件，暂时性错误按配置退避重试，
+    /// 返回投递状态和耗时。
+    ///
+    /// 服务商按收件人计数，因此每日配额和频率限制都按信封中的收件人数 (含抄送、密送) 计算；
+    /// 剩余配额不足时整封邮件排队。
+    async fn transmit(
+        &self,
+        email: &Message,
+        context: &DeliveryContext<'_>,
+    ) -> (SendStatus, Duration) {
+        let count = email.envelope().to().len();
+        if !context.quota.try_reserve(count as u32) {
+            return (SendStatus::Queued, Duration::ZERO);
+        }
+
+        let started = Instant::now();
+        let mut attempts = 0;
+        let status = loop {
+            let acquired = context.control.checkpoint().await
+                && context
+                    .control
+                    .unless_cancelled(context.limiter.acquire(count))
+                    .await
+                    .is_some();
+            if !acquired {
+                context.quota.release(count as u32);
+                break SendStatus::Cancelled;
+            }
+
+            attempts += 1;
+            let failure = match context.mailer.send(email.clone()).await {
+                Ok(response) => {
+                    break SendStatus::Sent {
+                        response: format!(
+                            "{} {}",
+                            response.code(),
+                            response.first_line().unwrap_or_default()
+                        ),
+                    };
+                }
+                Err(e) => SmtpFailure::classify(&e),
+            };
+
+            if !failure.transient || attempts > self.config.max_retries {
+                context.quota.release(count as u32);
+                break SendStatus::Failed {
+                    error: failure.message,
+                    smtp_code: failure.code,
+                    enhanced_code: failure.enhanced_code,
+                    attempts,
+                };
+            }
+
+            let base = Duration::from_millis(self.config.retry_base_delay_ms);
+            let backoff = tokio::time::sleep(retry::backoff(attempts, base));
+            if context.control.unless_cancelled(backoff).await.is_none() {
+                context.quota.release(count as u32);
+                break SendStatus::Cancelled;
+            }
+        };
+
+        (status, started.elapsed())
+    }
+}
+
+/// 逐层组织正文：HTML 和内嵌图片组成 `multipart/related`，与纯文本一起组成
+/// `multipart/alternative`，有附件时再与附件组成 `multipart/mixed`。
+fn message_body(
+    html: String,
+    text: String,
+    images: &[SinglePart],
+    attachments: Vec<SinglePart>,
+) -> MultiPart {
+    let alternative = MultiPart::alternative().singlepart(SinglePart::plain(text));
+    let html = SinglePart::html(html);
+    let alternative = if images.is_empty() {
+        alternative.singlepart(html)
+    } else {
+        alternative.multipart(
+            images
+                .iter()
+                .cloned()
+                .fold(MultiPart::related().singlepart(html), |related, image| {
+                    related.singlepart(image)
+                }),
+        )
+    };
+    if attachments.is_empty() {
+        return alternative;
+    }
+
+    attachments
+        .into_iter()
+        .fold(MultiPart::mixed().multipart(alternative), |mixed, part| {
+            mixed.singlepart(part)
+        })
+}
+
+/// 按配置的服务器、端口和加密方式创建 SMTP 传输，尚未设置认证信息。
+pub fn transport(config: &MailConfig) -> anyhow::Result<AsyncSmtpTransportBuilder> {
+    let host = config.smtp_server.as_str();
+    let builder = match config.tls_mode {
+        TlsMode::Wrapper => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+        TlsMode::StartTlsRequired => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+        TlsMode::StartTlsOpportunistic => {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+                .tls(Tls::Opportunistic(TlsParameters::new(host.to_string())?))
+        }
+        TlsMode::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+    };
+    Ok(builder.port(config.smtp_port))
+}
+
+/// 发送使用的共享 tokio 运行时。
+///
+/// 异步 SMTP 传输依赖 tokio，而图形界面运行在 GPUI 自己的执行器上，
+/// 因此发送任务统一交给这个运行时执行。
+pub fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .thread_name("batch-mail-sender")
+            .build()
+            .expect("创建 tokio 运行时失败")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> MailConfig {
+        MailConfig {
+            smtp_server: "127.0.0.1".to_string(),
+            tls_mode: TlsMode::None,
+            email_address: "me@example.com".to_string(),
+            password: "secret".to_string(),
+            sender_name: "Me".to_string(),
+            ..MailConfig::default()
+        }
+    }
+
+    fn closed_port() -> u16 {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    /// SMTP 服务器收到的一封邮件。
+    #[derive(Debug, Clone)]
+    struct Received {
+        rcpt: Vec<String>,
+        data: String,
+    }
+
+    /// 在本机启动一个接受一切的 SMTP 服务器，返回端口和收到的邮件。
+    async fn smtp_stub() -> (u16, Arc<std::sync::Mutex<Vec<Received>>>) {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let store = received.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    let (read, mut write) = stream.into_split();
+                    let mut lines = BufReader::new(read).lines();
+                    write.write_all(b"220 stub ready\r\n").await.ok();
+                    let mut rcpt = Vec::new();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let command = line.to_ascii_uppercase();
+                        let reply: &[u8] = if command.starts_with("EHLO") {
+                            b"250-stub\r\n250 AUTH PLAIN LOGIN\r\n"
+                        } else if command.starts_with("AUTH") {
+                            b"235 ok\r\n"
+                        } else if command.starts_with("RCPT TO:") {
+                            let address = line[8..].trim().trim_matches(['<', '>']);
+                            rcpt.push(address.to_string());
+                            b"250 ok\r\n"
+                        } else if command == "DATA" {
+                            write.write_all(b"354 go\r\n").await.ok();
+                            let mut data = String::new();
+                            while let Ok(Some(line)) = lines.next_line().await {
+                                if line == "." {
+                                    break;
+                                }
+                                data.push_str(&line);
+                                data.push('\n');
+                            }
+                            store.lock().unwrap().push(Received {
+                                rcpt: std::mem::take(&mut rcpt),
+                                data,
+                            });
+                            b"250 queued\r\n"
+                        } else if command == "QUIT" {
+                            write.write_all(b"221 bye\r\n").await.ok();
+                            break;
+                        } else {
+                            b"250 ok\r\n"
+                        };
+                        write.write_all(reply).await.ok();
+                    }
+                });
+            }
+        });
+        (port, received)
+    }
+
+    fn recipients(count: usize) -> Vec<Recipient> {
+        (0..count)
+            .map(|i| Recipient::new(format!("r{}@example.com", i)))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn bcc_batches_are_chunked_with_envelopes_of_their_own() {
+        let (port, received) = smtp_stub().await;
+        let config = MailConfig {
+            smtp_port: port,
+            max_concurrency: 1,
+            ..config()
+        };
+        let campaign = Campaign::new(config, recipients(5), "News", "<p>Hello all</p>").bcc_batch(
+            Some(BccBatch {
+                size: 2,
+                visible_to: "list@example.com".to_string(),
+            }),
+        );
+
+        let report = campaign.send().await.unwrap();
+        assert_eq!(report.sent_count(), 5);
+
+        let received = received.lock().unwrap().clone();
+        let envelopes: Vec<Vec<&str>> = received
+            .iter()
+            .map(|mail| mail.rcpt.iter().map(String::as_str).collect())
+            .collect();
+        assert_eq!(
+            envelopes,
+            [
+                vec!["r0@example.com", "r1@example.com"],
+                vec!["r2@example.com", "r3@example.com"],
+                vec!["r4@example.com"],
+            ]
+        );
+        for mail in &received {
+            assert!(mail.data.contains("To: list@example.com"));
+            assert!(!mail.data.contains("Bcc:"));
+            assert!(!mail.data.contains("r0@example.com"));
+            assert!(!mail.data.contains("r4@example.com"));
+        }
+    }
+
+    #[tokio::test]
+    async fn bcc_batches_leave_out_skipped_addresses() {
+        let (port, received) = smtp_stub().await;
+        let config = MailConfig {
+            smtp_port: port,
+            ..config()
+        };
+        let mut list = recipients(3);
+        list[1].email = "not-an-address".to_string();
+        let campaign = Campaign::new(config, list, "News", "<p>Hello all</p>")
+            .skip_invalid(true)
+            .bcc_batch(Some(BccBatch {
+                size: 10,
+                visible_to: String::new(),
+            }));
+
+        let report = campaign.send().await.unwrap();
+
+        assert!(matches!(
+            report.results[1].status,
+            SendStatus::Skipped { .. }
+        ));
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].rcpt, ["r0@example.com", "r2@example.com"]);
+    }
+
+    #[tokio::test]
+    async fn bcc_batch_without_visible_to_shows_the_sender() {
+        let (port, received) = smtp_stub().await;
+        let config = MailConfig {
+            smtp_port: port,
+            ..config()
+        };
+        let campaign = Campaign::new(config, recipients(3), "News", "<p>Hello all</p>")
+            .reply_to("replies@example.com")
+            .bcc_batch(Some(BccBatch {
+                size: 10,
+                visible_to: String::new(),
+            }));
+
+        campaign.send().await.unwrap();
+
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].rcpt.len(), 3);
+        assert!(!received[0].rcpt.contains(&"me@example.com".to_string()));
+        assert!(received[0].data.contains("To: Me <me@example.com>"));
+        assert!(received[0].data.contains("Reply-To: replies@example.com"));
+    }
+
+    #[test]
+    fn bcc_batch_must_fit_every_limit() {
+        let recipients: Vec<Recipient> = (0..5)
+            .map(|i| Recipient::new(format!("r{}@example.com", i)))
+            .collect();
+        let batch = |size| {
+            Some(BccBatch {
+                size,
+                visible_to: String::new(),
+            })
+        };
+
+        for (per_second, per_minute, daily_cap) in [(2, 0, 0), (0, 2, 0), (0, 0, 2)] {
+            let config = MailConfig {
+                max_per_second: per_second,
+                max_per_minute: per_minute,
+                daily_cap,
+                ..config()
+            };
+            let campaign =
+                Campaign::new(config, recipients.clone(), "Hi", "<p>Hi</p>").bcc_batch(batch(3));
+            let error = campaign.validate().unwrap_err().to_string();
+            assert!(error.contains("不能超过"), "{}", error);
+
+            let campaign = campaign.bcc_batch(batch(2));
+            assert!(campaign.validate().is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_copy_goes_only_to_test_addresses_with_first_recipient_data() {
+        let (port, received) = smtp_stub().await;
+        let config = MailConfig {
+            smtp_port: port,
+            test_addresses: vec!["qa@example.com".to_string()],
+            ..config()
+        };
+        let mut list = recipients(2);
+        list[0].email = "broken".to_string();
+        list[1].fields.insert("name".to_string(), "Ann".to_string());
+        let campaign = Campaign::new(config, list, "Hi {{name}}", "<p>Dear {{name}}</p>")
+            .cc("boss@example.com")
+            .skip_invalid(true);
+
+        let test = campaign.test_copy().unwrap();
+        test.validate().unwrap();
+        let report = test.send().await.unwrap();
+
+        assert_eq!(report.results.len(), 1);
+        assert_eq!(report.results[0].email, "qa@example.com");
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].rcpt, ["qa@example.com"]);
+        assert!(received[0].data.contains("Subject: [TEST] Hi Ann"));
+        assert!(received[0].data.contains("Dear Ann"));
+        assert!(!received[0].data.contains("boss@example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_copy_embeds_images_like_the_real_send() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["logo.png", "photo.png"] {
+            std::fs::write(dir.path().join(name), b"\x89PNG\r\n\x1a\n").unwrap();
+        }
+        let (port, received) = smtp_stub().await;
+        let config = MailConfig {
+            smtp_port: port,
+            ..config()
+        };
+        let mut list = recipients(1);
+        list[0]
+            .fields
+            .insert("photo".to_string(), "photo.png".to_string());
+        let campaign = Campaign::new(
+            config,
+            list,
+            "Hi",
+            r#"<img src="logo.png"><img src="{{photo}}">"#,
+        )
+        .base_dir(Some(dir.path().to_path_buf()));
+
+        campaign.send().await.unwrap();
+        campaign.test_copy().unwrap().send().await.unwrap();
+
+        let received = received.lock().unwrap().clone();
+        let images = |data: &str| data.matches("Content-ID:").count();
+        assert_eq!(received.len(), 2);
+        assert_eq!(images(&received[0].data), 1);
+        assert_eq!(images(&received[1].data), images(&received[0].data));
+    }
+
+    #[tokio::test]
+    async fn cancel_interrupts_retry_backoff() {
+        let mut config = config();
+        config.smtp_port = closed_port();
+        config.max_retries = 3;
+        config.retry_base_delay_ms = 60_000;
+        let campaign = Campaign::new(
+            config,
+            vec![Recipient::new("a@example.com")],
+            "Hi",
+            "<p>Hi</p>",
+        );
+
+        let control = SendControl::new();
+        let canceller = control.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            canceller.cancel();
+        });
+
+        let started = Instant::now();
+        let report = campaign
+            .send_with_progress(&control, |_, _| {})
+            .await
+            .unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(report.results[0].status, SendStatus::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn cancel_interrupts_rate_limit_wait() {
+        let mut config = config();
+        config.smtp_port = closed_port();
+        config.max_retries = 0;
+        config.max_per_minute = 1;
+        config.max_concurrency = 1;
+        let recipients = vec![
+            Recipient::new("a@example.com"),
+            Recipient::new("b@example.com"),
+        ];
+        let campaign = Campaign::new(config, recipients, "Hi", "<p>Hi</p>");
+
+        let control = SendControl::new();
+        let canceller = control.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            canceller.cancel();
+        });
+
+        let started = Instant::now();
+        let report = campaign
+            .send_with_progress(&control, |_, _| {})
+            .await
+            .unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(matches!(
+            report.results[0].status,
+            SendStatus::Failed { .. }
+        ));
+        assert_eq!(report.results[1].status, SendStatus::Cancelled);
+    }
+
+    #[test]
+    fn check_attachments_reports_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("invoices")).unwrap();
+        std::fs::write(dir.path().join("invoices/r0.pdf"), b"%PDF").unwrap();
+
+        let mut list = recipients(2);
+        for (index, recipient) in list.iter_mut().enumerate() {
+            recipient
+                .fields
+                .insert("id".to_string(), format!("r{}", index));
+        }
+        let campaign = Campaign::new(config(), list, "Hi", "<p>Hi</p>")
+            .personal_attachment(Some("invoices/{{id}}.pdf".to_string()))
+            .base_dir(Some(dir.path().to_path_buf()));
+
+        let error = campaign.check_attachments().unwrap_err().to_string();
+        assert!(error.contains("1 个收件人的附件不存在"), "{error}");
+        assert!(error.contains("r1@example.com"), "{error}");
+        assert!(!error.contains("r0@example.com"), "{error}");
+
+        let mut campaign = campaign;
+        campaign.recipients[1].fields.remove("id");
+        let error = campaign.check_attachments().unwrap_err().to_string();
+        assert!(error.contains("缺少附件路径所需的合并字段"), "{error}");
+    }
+}
@@ -0,0 +1,211 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use anyhow::Context;
+use argon2::Argon2;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng, rand_core::RngCore},
+};
+
+use crate::mail_config::{self, MailConfig};
+
+/// 命令行读取主密码的环境变量。
+pub const PASSPHRASE_ENV: &str = "BATCH_MAIL_PASSPHRASE";
+
+/// `secrets.json` 的磁盘格式：整个密码表加密成一段密文。
+#[derive(serde::Serialize, serde::Deserialize)]
+struct VaultFile {
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// 用主密码加密保存的账号密码表 (账号名称 → 密码)。
+///
+/// 密钥由 Argon2id 从主密码派生，内容使用 ChaCha20-Poly1305 加密。
+pub struct Vault {
+    path: PathBuf,
+    key: Key,
+    salt: [u8; 16],
+    secrets: BTreeMap<String, String>,
+}
+
+impl Vault {
+    pub fn path() -> anyhow::Result<PathBuf> {
+        Ok(MailConfig::config_dir()?.join("secrets.json"))
+    }
+
+    pub fn exists() -> bool {
+        Self::path().is_ok_and(|path| path.exists())
+    }
+
+    /// 用主密码解锁密码库；密码库还不存在时以该主密码新建一个空的。
+    pub fn unlock(passphrase: &str) -> anyhow::Result<Self> {
+        Self::unlock_at(Self::path()?, passphrase)
+    }
+
+    /// 与 [`Vault::unlock`] 相同，但使用指定位置的密码库文件。
+    pub(crate) fn unlock_at(path: PathBuf, passphrase: &str) -> anyhow::Result<Self> {
+        if passphrase.is_empty() {
+            anyhow::bail!("主密码不能为空");
+        }
+
+        if !path.exists() {
+            let mut salt = [0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            return Ok(Self {
+                key: derive_key(passphrase, &salt)?,
+                path,
+                salt,
+                secrets: BTreeMap::new(),
+            });
+        }
+
+        let content = fs::read_to_string(&path).context("读取密码库失败")?;
+        let file: VaultFile = serde_json::from_str(&content).context("解析密码库失败")?;
+        let salt: [u8; 16] = decode(&file.salt)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("密码库格式错误"))?;
+        let nonce = decode(&file.nonce)?;
+        if nonce.len() != 12 {
+            anyhow::bail!("密码库格式错误");
+        }
+
+        let key = derive_key(passphrase, &salt)?;
+        let plaintext = ChaCha20Poly1305::new(&key)
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                decode(&file.ciphertext)?.as_slice(),
+            )
+            .map_err(|_| anyhow::anyhow!("主密码错误"))?;
+        let secrets = serde_json::from_slice(&plaintext).context("解析密码库失败")?;
+
+        Ok(Self {
+            path,
+            key,
+            salt,
+            secrets,
+        })
+    }
+
+    pub fn password(&self, profile: &str) -> Option<&str> {
+        self.secrets.get(profile).map(|s| s.as_str())
+    }
+
+    pub fn set_password(&mut self, profile: &str, password: &str) {
+        if password.is_empty() {
+            self.secrets.remove(profile);
+        } else {
+            self.secrets
+                .insert(profile.to_string(), password.to_string());
+        }
+    }
+
+    /// 删除不在 `profiles` 中的账号密码。
+    pub fn retain(&mut self, profiles: &[String]) {
+        self.secrets.retain(|name, _| profiles.contains(name));
+    }
+
+    /// 每次保存都使用新的随机 nonce 重新加密整个密码表。
+    pub fn save(&self) -> anyhow::Result<()> {
+        let plaintext = serde_json::to_vec(&self.secrets)?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(&self.key)
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| anyhow::anyhow!("加密密码库失败"))?;
+
+        let file = VaultFile {
+            salt: STANDARD.encode(self.salt),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        };
+        let json = serde_json::to_string_pretty(&file)?;
+        mail_config::write_private(&self.path, &json).context("写入密码库失败")
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> anyhow::Result<Key> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("派生密钥失败: {}", e))?;
+    Ok(key)
+}
+
+fn decode(text: &str) -> anyhow::Result<Vec<u8>> {
+    STANDARD.decode(text).context("密码库格式错误")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_save_and_unlock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secrets.json");
+
+        let mut vault = Vault::unlock_at(path.clone(), "correct horse").unwrap();
+        assert_eq!(vault.password("work"), None);
+        vault.set_password("work", "smtp-secret");
+        vault.set_password("home", "другой пароль");
+        vault.save().unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("smtp-secret"));
+
+        let vault = Vault::unlock_at(path, "correct horse").unwrap();
+        assert_eq!(vault.password("work"), Some("smtp-secret"));
+        assert_eq!(vault.password("home"), Some("другой пароль"));
+    }
+
+    #[test]
+    fn rejects_wrong_or_empty_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secrets.json");
+
+        let mut vault = Vault::unlock_at(path.clone(), "right").unwrap();
+        vault.set_password("work", "smtp-secret");
+        vault.save().unwrap();
+
+        let error = Vault::unlock_at(path.clone(), "wrong").err().unwrap();
+        assert_eq!(error.to_string(), "主密码错误");
+        assert!(Vault::unlock_at(path, "").is_err());
+    }
+
+    #[test]
+    fn rejects_tampered_ciphertext() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secrets.json");
+
+        let mut vault = Vault::unlock_at(path.clone(), "right").unwrap();
+        vault.set_password("work", "smtp-secret");
+        vault.save().unwrap();
+
+        let mut file: VaultFile =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let mut ciphertext = decode(&file.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        file.ciphertext = STANDARD.encode(ciphertext);
+        fs::write(&path, serde_json::to_string(&file).unwrap()).unwrap();
+
+        assert!(Vault::unlock_at(path, "right").is_err());
+    }
+
+    #[test]
+    fn set_password_and_retain_update_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut vault = Vault::unlock_at(dir.path().join("secrets.json"), "pw").unwrap();
+
+        vault.set_password("a", "1");
+        vault.set_password("b", "2");
+        vault.set_password("c", "3");
+        vault.set_password("b", "");
+        assert_eq!(vault.password("b"), None);
+
+        vault.retain(&["a".to_string(), "b".to_string()]);
+        assert_eq!(vault.password("a"), Some("1"));
+        assert_eq!(vault.password("c"), None);
+    }
+}
@@ -4,7 +4,7 @@ use gpui::{AnyView, AppContext, Context, Entity, EventEmitter, Render, Window};
 
 use crate::{
     events::Events,
-    views::{HomeView, SettingsView, Views},
+    views::{HistoryView, HomeView, SettingsView, UnlockView, Views},
 };
 
 pub struct AppView {
@@ -15,21 +15,57 @@ pub struct AppView {
 impl AppView {
     pub fn new() -> Self {
         Self {
-            active_view: Views::HomeView,
+            active_view: Views::UnlockView,
             views: HashMap::new(),
         }
     }
 
-    fn observe_view<T: EventEmitter<Events>>(view: &Entity<T>, cx: &mut Context<Self>) {
-        cx.subscribe(view, |this, _, event, cx| match event {
+    fn observe_view<T: EventEmitter<Events>>(
+        view: &Entity<T>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        cx.subscribe_in(view, window, |this, _, event, window, cx| match event {
             Events::ViewChanged(new_view) => {
                 this.active_view = *new_view;
+                // 视图会被缓存，切换时重新读取可能已在别处修改的数据。
+                match new_view {
+                    Views::HomeView => {
+                        if let Some(home) = this.cached::<HomeView>(Views::HomeView) {
+                            home.update(cx, |home, cx| home.reload_profiles(cx));
+                        }
+                    }
+                    Views::SettingsView => {
+                        if let Some(settings) = this.cached::<SettingsView>(Views::SettingsView) {
+                            settings.update(cx, |settings, cx| settings.reload(window, cx));
+                        }
+                    }
+                    Views::HistoryView => {
+                        if let Some(history) = this.cached::<HistoryView>(Views::HistoryView) {
+                            history.update(cx, |history, cx| history.reload(cx));
+                        }
+                    }
+                    Views::UnlockView => {}
+                }
+                cx.notify();
+            }
+            Events::RetryCampaign(record) => {
+                if let Some(home) = this.cached::<HomeView>(Views::HomeView) {
+                    home.update(cx, |home, cx| home.load_retry(record, window, cx));
+                }
+                this.active_view = Views::HomeView;
                 cx.notify();
             }
         })
         .detach();
     }
 
+    fn cached<T: 'static>(&self, view_type: Views) -> Option<Entity<T>> {
+        self.views
+            .get(&view_type)
+            .and_then(|v| v.clone().downcast::<T>().ok())
+    }
+
     fn get_or_create_view(
         &mut self,
         window: &mut Window,
@@ -43,12 +79,22 @@ impl AppView {
         let view: AnyView = match view_type {
             Views::HomeView => {
                 let v = cx.new(|cx| HomeView::new(window, cx));
-                Self::observe_view(&v, cx);
+                Self::observe_view(&v, window, cx);
                 v.into()
             }
             Views::SettingsView => {
                 let v = cx.new(|cx| SettingsView::new(window, cx));
-                Self::observe_view(&v, cx);
+                Self::observe_view(&v, window, cx);
+                v.into()
+            }
+            Views::UnlockView => {
+                let v = cx.new(|cx| UnlockView::new(window, cx));
+                Self::observe_view(&v, window, cx);
+                v.into()
+            }
+            Views::HistoryView => {
+                let v = cx.new(HistoryView::new);
+                Self::observe_view(&v, window, cx);
                 v.into()
             }
         };
@@ -0,0 +1,252 @@
+use gpui::{
+    Context, EventEmitter, InteractiveElement, IntoElement, ParentElement, Render,
+    StatefulInteractiveElement, Styled, div, prelude::FluentBuilder, px, rgb,
+};
+use gpui_component::{StyledExt, button::Button, label::Label, scroll::ScrollableElement};
+
+use batch_mail::history::CampaignRecord;
+
+use crate::{
+    events::Events,
+    views::{Views, send_progress::status_style},
+};
+
+struct HistoryEntry {
+    record: CampaignRecord,
+    /// 需要重试的收件人数，加载时计算一次。
+    retry_count: usize,
+}
+
+/// 浏览已保存的发送记录，点击一条记录展开每个收件人的结果。
+pub struct HistoryView {
+    records: Vec<HistoryEntry>,
+    selected: Option<String>,
+    error: Option<String>,
+}
+
+impl HistoryView {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        let mut view = Self {
+            records: Vec::new(),
+            selected: None,
+            error: None,
+        };
+        view.reload(cx);
+        view
+    }
+
+    pub fn reload(&mut self, cx: &mut Context<Self>) {
+        match CampaignRecord::load_all() {
+            Ok(records) => {
+                self.records = records
+                    .into_iter()
+                    .map(|record| HistoryEntry {
+                        retry_count: record.retry_count(),
+                        record,
+                    })
+                    .collect();
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("读取发送记录失败: {}", e)),
+        }
+        cx.notify();
+    }
+
+    fn toggle_record(&mut self, id: String, cx: &mut Context<Self>) {
+        self.selected = if self.selected.as_ref() == Some(&id) {
+            None
+        } else {
+            Some(id)
+        };
+        cx.notify();
+    }
+
+    fn render_record(
+        &self,
+        index: usize,
+        entry: &HistoryEntry,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let record = &entry.record;
+        let retry_count = entry.retry_count;
+        let expanded = self.selected.as_ref() == Some(&record.id);
+        let id = record.id.clone();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .p_4()
+            .bg(rgb(0x27272a))
+            .rounded_lg()
+            .child(
+                div()
+                    .id(("history-record", index))
+                    .flex()
+                    .flex_col()
+                    .gap_1()
+                    .cursor_pointer()
+                    .on_click(cx.listener(move |view, _, _, cx| {
+                        view.toggle_record(id.clone(), cx);
+                    }))
+                    .child(
+                        div()
+                            .text_sm()
+                            .font_semibold()
+                            .text_color(rgb(0xe4e4e7))
+                            .truncate()
+                            .child(record.subject.clone()),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .justify_between()
+                            .text_xs()
+                            .text_color(rgb(0x71717a))
+                            .child(format!("{} · {}", record.formatted_time(), record.account))
+                            .child(format!(
+                                "成功 {}/{}",
+                                record.sent_count(),
+                                record.results.len()
+                            )),
+                    ),
+            )
+            .when(expanded, |this| {
+                let template = record
+                    .template_path
+                    .as_ref()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|| "未知".to_string());
+
+                this.child(
+                    div()
+                        .flex()
+                        .justify_between()
+                        .items_center()
+                        .gap_3()
+                        .child(
+                            div()
+                                .flex_1()
+                                .truncate()
+                                .text_xs()
+                                .text_color(rgb(0x71717a))
+                                .child(format!("模板: {}", template)),
+                        )
+                        .when(retry_count > 0, |this| {
+                            this.child(
+                                Button::new(("retry-record", index))
+                                    .label(format!("重试失败的 {} 个", retry_count))
+                                    .compact()
+                                    .on_click(cx.listener(move |view, _, _, cx| {
+                                        if let Some(entry) = view.records.get(index) {
+                                            cx.emit(Events::RetryCampaign(Box::new(
+                                                entry.record.clone(),
+                                            )));
+                                        }
+                                    })),
+                            )
+                        }),
+                )
+                .child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .bg(rgb(0x18181b))
+                        .rounded_md()
+                        .children(record.results.iter().map(|result| {
+                            let (label, color) = status_style(&result.status);
+                            div()
+                                .flex()
+                                .items_center()
+                                .gap_3()
+                                .h(px(28.0))
+                                .px_3()
+                                .text_xs()
+                                .border_b_1()
+                                .border_color(rgb(0x27272a))
+                                .child(
+                                    div()
+                                        .w(px(160.0))
+                                        .truncate()
+                                        .text_color(rgb(0xe4e4e7))
+                                        .child(result.email.clone()),
+                                )
+                                .child(div().w(px(48.0)).text_color(color).child(label))
+                                .child(
+                                    div()
+                                        .flex_1()
+                                        .truncate()
+                                        .text_color(rgb(0xa1a1aa))
+                                        .child(result.status.detail()),
+                                )
+                        })),
+                )
+            })
+    }
+}
+
+impl EventEmitter<Events> for HistoryView {}
+
+impl Render for HistoryView {
+    fn render(
+        &mut self,
+        _window: &mut gpui::Window,
+        cx: &mut gpui::Context<Self>,
+    ) -> impl gpui::IntoElement {
+        let view_handle = cx.entity();
+        let records: Vec<_> = self
+            .records
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| self.render_record(index, entry, cx).into_any_element())
+            .collect();
+
+        div()
+            .id("history-view")
+            .size_full()
+            .bg(rgb(0x18181b))
+            .flex()
+            .flex_col()
+            .child(
+                div()
+                    .flex()
+                    .justify_between()
+                    .p_4()
+                    .border_b_1()
+                    .border_color(rgb(0x27272a))
+                    .child(Label::new("发送记录").text_xl().text_color(rgb(0xe4e4e7)))
+                    .child(
+                        Button::new("back-btn")
+                            .label("返回")
+                            .on_click(move |_, _, cx| {
+                                view_handle.update(cx, |_, cx| {
+                                    cx.emit(Events::ViewChanged(Views::HomeView));
+                                });
+                            }),
+                    ),
+            )
+            .child(
+                div()
+                    .id("history-list")
+                    .flex()
+                    .flex_col()
+                    .gap_3()
+                    .p_6()
+                    .overflow_y_scroll()
+                    .overflow_scrollbar()
+                    .flex_1()
+                    .when_some(self.error.clone(), |this, error| {
+                        this.child(div().text_sm().text_color(rgb(0xf87171)).child(error))
+                    })
+                    .when(records.is_empty() && self.error.is_none(), |this| {
+                        this.child(
+                            div()
+                                .text_sm()
+                                .text_color(rgb(0x71717a))
+                                .child("还没有发送记录"),
+                        )
+                    })
+                    .children(records),
+            )
+    }
+}
@@ -1,20 +1,39 @@
 use std::path::PathBuf;
 
+use anyhow::Context as _;
+
 use gpui::{
     AppContext, AsyncApp, Context, Entity, EventEmitter, InteractiveElement, IntoElement,
-    ParentElement, Render, StatefulInteractiveElement, Styled, WeakEntity, Window, div,
-    prelude::FluentBuilder, px, rgb,
+    ParentElement, Render, SharedString, StatefulInteractiveElement, Styled, Subscription,
+    WeakEntity, Window, div, prelude::FluentBuilder, px, rgb,
 };
 use gpui_component::{
-    IconName, StyledExt,
-    button::Button,
-    input::{Input, InputState},
+    Disableable, IconName, Selectable, StyledExt,
+    button::{Button, ButtonVariants},
+    checkbox::Checkbox,
+    input::{Input, InputEvent, InputState},
     label::Label,
     scroll::ScrollableElement,
-    v_flex,
+    text::TextView,
+};
+
+use batch_mail::{
+    attachments::{self, Attachment},
+    css_inline::{self, InlineReport},
+    history::CampaignRecord,
+    html,
+    inline_images::{self, LocalImage},
+    merge,
+    recipients::{
+        self, ColumnMapping, CopyField, InvalidRecipient, Recipient, RecipientTable, RejectedRow,
+    },
+    sender::{self, BccBatch, Campaign, CampaignReport, RunState, SendControl},
 };
 
-use crate::{events::Events, mail_config::MailConfig, views::Views};
+use crate::{
+    events::Events,
+    views::{Views, send_progress::SendProgress, unlock_view::load_profiles},
+};
 
 #[derive(Clone, Debug)]
 enum SendingState {
@@ -24,12 +43,107 @@ enum SendingState {
     Error(String),
 }
 
+struct RecipientImport {
+    path: PathBuf,
+    table: RecipientTable,
+    mapping: ColumnMapping,
+}
+
+/// 预览区的显示方式。
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PreviewMode {
+    /// 去掉样式后的纯文本，接近不显示 HTML 的客户端看到的内容。
+    Text,
+    /// 近似的排版效果，不支持完整的 CSS。
+    Styled,
+}
+
+/// 收件人输入或导入表格解析后的结果，来源变化时由 [`HomeView::refresh_recipients`] 更新。
+#[derive(Default)]
+struct RecipientList {
+    recipients: Vec<Recipient>,
+    /// 列数不符或重复而被拒绝的行。
+    rejected: Vec<RejectedRow>,
+    invalid: Vec<InvalidRecipient>,
+}
+
+/// 模板引用的本地图片，模板变化时由 [`HomeView::refresh_template`] 更新。
+#[derive(Default)]
+struct ImageCheck {
+    total: usize,
+    missing: Vec<LocalImage>,
+}
+
+/// 预览区显示的内容，模板、主题、收件人或预览序号变化时由 [`HomeView::refresh_preview`] 更新。
+struct Preview {
+    uses_fields: bool,
+    index: usize,
+    count: usize,
+    /// 当前预览的收件人及序号说明。
+    position: String,
+    subject: String,
+    body: SharedString,
+    text: SharedString,
+    missing: String,
+}
+
+/// 个性化附件检查的结果。
+enum AttachmentCheck {
+    /// 检查过的收件人都找到了附件。
+    AllFound(usize),
+    Failed(String),
+}
+
+/// 从上一次发送 (或发送记录) 中挑出的待重试收件人，存在时代替收件人输入。
+struct RetrySource {
+    subject: String,
+    recipients: Vec<Recipient>,
+    /// 原发送使用的发件地址，见 [`CampaignRecord::account`]。
+    account: String,
+    /// 当前选用的账号与原发送账号不一致时的提示。
+    account_warning: Option<String>,
+}
+
 pub struct HomeView {
     selected_file: Option<PathBuf>,
     html_content: Option<String>,
+    inline_css: bool,
+    /// 开启 CSS 内联时当前模板的内联结果。
+    css_report: Option<InlineReport>,
+    image_check: ImageCheck,
+    attachments: Vec<Attachment>,
+    personal_attachment_input: Entity<InputState>,
+    attachment_check: Option<AttachmentCheck>,
+    recipient_import: Option<RecipientImport>,
     recipients_input: Entity<InputState>,
+    recipient_list: RecipientList,
     subject_input: Entity<InputState>,
+    cc_input: Entity<InputState>,
+    bcc_input: Entity<InputState>,
+    reply_to_input: Entity<InputState>,
+    /// 是否使用密送群发，见 [`BccBatch`]。
+    bcc_batch: bool,
+    batch_size_input: Entity<InputState>,
+    batch_to_input: Entity<InputState>,
+    /// 可选的手写纯文本模板，留空时由 HTML 自动转换。
+    text_input: Entity<InputState>,
     sending_state: SendingState,
+    skip_invalid: bool,
+    send_progress: Option<SendProgress>,
+    send_control: Option<SendControl>,
+    retry: Option<RetrySource>,
+    last_record: Option<CampaignRecord>,
+    /// 上一次发送中需要重试的收件人数，发送完成时计算一次。
+    last_retry_count: usize,
+    sending_test: bool,
+    profile_names: Vec<String>,
+    /// 本次发送使用的账号名称。
+    profile: String,
+    preview_mode: PreviewMode,
+    /// 预览中用于合并字段的收件人序号。
+    preview_index: usize,
+    preview: Option<Preview>,
+    _subscriptions: Vec<Subscription>,
 }
 
 impl HomeView {
@@ -42,14 +156,133 @@ impl HomeView {
                 .auto_grow(1, 10)
         });
         let subject_input = cx.new(|cx| InputState::new(window, cx).placeholder("邮件主题"));
+        let cc_input =
+            cx.new(|cx| InputState::new(window, cx).placeholder("抄送 (可选，多个地址以逗号分隔)"));
+        let bcc_input = cx.new(|cx| InputState::new(window, cx).placeholder("密送 (可选)"));
+        let reply_to_input =
+            cx.new(|cx| InputState::new(window, cx).placeholder("回复地址 (可选，默认为发件人)"));
+        let batch_size_input = cx.new(|cx| {
+            let mut input = InputState::new(window, cx).placeholder("每批人数");
+            input.set_value("50", window, cx);
+            input
+        });
+        let batch_to_input = cx.new(|cx| {
+            InputState::new(window, cx).placeholder("信头显示的收件人 (可选，默认为发件人自己)")
+        });
+        let text_input = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("纯文本正文 (可选，留空则由 HTML 自动转换)")
+                .multi_line(true)
+                .rows(6)
+                .auto_grow(1, 10)
+        });
+        let personal_attachment_input = cx.new(|cx| {
+            InputState::new(window, cx).placeholder("例如 invoices/{{id}}.pdf 或 {{attachment}}")
+        });
+
+        let refresh_preview =
+            |view: &mut Self, _: Entity<InputState>, event: &InputEvent, cx: &mut Context<Self>| {
+                if matches!(event, InputEvent::Change) {
+                    view.refresh_preview(cx);
+                }
+            };
+        let _subscriptions = vec![
+            cx.subscribe(&recipients_input, |view, _, event: &InputEvent, cx| {
+                if matches!(event, InputEvent::Change) {
+                    view.refresh_recipients(cx);
+                }
+            }),
+            cx.subscribe(&subject_input, refresh_preview),
+            cx.subscribe(&text_input, refresh_preview),
+        ];
 
-        Self {
+        let mut view = Self {
             selected_file: None,
             html_content: None,
+            inline_css: false,
+            css_report: None,
+            image_check: ImageCheck::default(),
+            attachments: Vec::new(),
+            personal_attachment_input,
+            attachment_check: None,
+            recipient_import: None,
             recipients_input,
+            recipient_list: RecipientList::default(),
             subject_input,
+            cc_input,
+            bcc_input,
+            reply_to_input,
+            bcc_batch: false,
+            batch_size_input,
+            batch_to_input,
+            text_input,
             sending_state: SendingState::Idle,
+            skip_invalid: false,
+            send_progress: None,
+            send_control: None,
+            retry: None,
+            last_record: None,
+            last_retry_count: 0,
+            sending_test: false,
+            profile_names: Vec::new(),
+            profile: String::new(),
+            preview_mode: PreviewMode::Text,
+            preview_index: 0,
+            preview: None,
+            _subscriptions,
+        };
+        view.reload_profiles(cx);
+        view
+    }
+
+    pub fn reload_profiles(&mut self, cx: &mut Context<Self>) {
+        let profiles = load_profiles(cx).unwrap_or_default();
+        self.profile_names = profiles.names();
+        if !self.profile_names.contains(&self.profile) {
+            self.profile = profiles.profiles[profiles.active_index()].name.clone();
         }
+        self.check_retry_account(cx);
+        cx.notify();
+    }
+
+    /// 切换发件账号，并记为下次启动时默认选用的账号。
+    fn select_profile(&mut self, name: String, cx: &mut Context<Self>) {
+        match load_profiles(cx) {
+            Ok(mut profiles) => {
+                profiles.active = name.clone();
+                if let Err(e) = profiles.save() {
+                    eprintln!("保存选用账号失败: {:?}", e);
+                }
+            }
+            Err(e) => eprintln!("读取配置失败: {:?}", e),
+        }
+        self.profile = name;
+        self.check_retry_account(cx);
+        cx.notify();
+    }
+
+    /// 重试时检查当前选用的账号是否就是原发送使用的账号。
+    fn check_retry_account(&mut self, cx: &mut Context<Self>) {
+        let Some(retry) = &self.retry else {
+            return;
+        };
+        let current = load_profiles(cx)
+            .and_then(|p| p.config(Some(&self.profile)))
+            .map(|config| config.email_address)
+            .unwrap_or_default();
+        let warning = (!current.eq_ignore_ascii_case(&retry.account)).then(|| {
+            format!(
+                "原发送使用的账号是 {}，当前选用的账号 {} ({}) 与之不同",
+                retry.account, self.profile, current
+            )
+        });
+        if let Some(retry) = &mut self.retry {
+            retry.account_warning = warning;
+        }
+    }
+
+    pub fn send_progress(&self) -> Option<&SendProgress> {
+        self.send_progress.as_ref()
     }
 
     fn select_file(&mut self, cx: &mut Context<Self>) {
@@ -73,6 +306,8 @@ impl HomeView {
                                 view.update(&mut cx, |this, cx| {
                                     this.selected_file = Some(path);
                                     this.html_content = Some(content);
+                                    this.preview_index = 0;
+                                    this.refresh_template(cx);
                                     cx.notify();
                                 })
                                 .ok();
@@ -95,66 +330,486 @@ impl HomeView {
         .detach();
     }
 
-    fn send_email(&mut self, cx: &mut Context<Self>) {
-        let html_content = match &self.html_content {
-            Some(content) => content.clone(),
-            None => {
-                self.sending_state = SendingState::Error("请先选择 HTML 文件".to_string());
-                cx.notify();
+    /// 模板或内联设置变化后，重新计算 CSS 内联结果 (未开启内联时清空)、本地图片检查和预览。
+    fn refresh_template(&mut self, cx: &mut Context<Self>) {
+        self.css_report = self
+            .html_content
+            .as_deref()
+            .filter(|_| self.inline_css)
+            .map(css_inline::inline);
+
+        let base_dir = self.selected_file.as_ref().and_then(|p| p.parent());
+        self.image_check = match &self.html_content {
+            Some(html) => {
+                let images = inline_images::local_images(html, base_dir);
+                ImageCheck {
+                    total: images.len(),
+                    missing: images.into_iter().filter(|i| !i.path.is_file()).collect(),
+                }
+            }
+            None => ImageCheck::default(),
+        };
+        self.refresh_preview(cx);
+    }
+
+    /// 收件人输入或导入的表格、列映射变化后重新解析收件人。
+    fn refresh_recipients(&mut self, cx: &mut Context<Self>) {
+        let (recipients, rejected) = match &self.recipient_import {
+            Some(import) => import.table.to_recipients(&import.mapping),
+            None => recipients::parse_recipients(&self.recipients_input.read(cx).value()),
+        };
+        self.recipient_list = RecipientList {
+            invalid: recipients::find_invalid(&recipients),
+            recipients,
+            rejected,
+        };
+        self.refresh_preview(cx);
+    }
+
+    /// 按当前模板、主题、纯文本模板和预览的收件人重新生成预览内容。
+    fn refresh_preview(&mut self, cx: &mut Context<Self>) {
+        let template = match (&self.css_report, &self.html_content) {
+            (Some(report), _) => &report.html,
+            (None, Some(template)) => template,
+            (None, None) => {
+                self.preview = None;
                 return;
             }
         };
 
-        let recipients_text = self.recipients_input.read(cx).value().to_string();
-        let subject = self.subject_input.read(cx).value().to_string();
+        let subject_template = self.subject_input.read(cx).value().to_string();
+        let text_template =
+            Some(self.text_input.read(cx).value().to_string()).filter(|t| !t.trim().is_empty());
+        let uses_fields = [
+            Some(template.as_str()),
+            Some(&subject_template),
+            text_template.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|t| !merge::placeholders(t).is_empty());
+        let recipients = if uses_fields {
+            self.current_recipients()
+        } else {
+            &[]
+        };
+        let index = self.preview_index.min(recipients.len().saturating_sub(1));
+        let recipient = recipients.get(index);
 
-        if recipients_text.trim().is_empty() {
-            self.sending_state = SendingState::Error("请输入收件人地址".to_string());
+        let (subject, body, text) = match recipient {
+            Some(recipient) => (
+                merge::render_partial(&subject_template, recipient),
+                merge::render_partial_html(template, recipient),
+                text_template
+                    .as_ref()
+                    .map(|t| merge::render_partial(t, recipient)),
+            ),
+            None => (subject_template, template.clone(), text_template.clone()),
+        };
+        let mut templates = vec![template.as_str(), &subject];
+        templates.extend(text_template.as_deref());
+        let missing = recipient
+            .map(|r| merge::describe_missing(&merge::find_missing(&templates, [r])))
+            .unwrap_or_default();
+        let position = match recipient {
+            Some(recipient) => format!("{} ({}/{})", recipient.email, index + 1, recipients.len()),
+            None => "没有收件人，占位符未替换".to_string(),
+        };
+
+        self.preview = Some(Preview {
+            uses_fields,
+            index,
+            count: recipients.len(),
+            position,
+            subject,
+            text: text.unwrap_or_else(|| html::to_text(&body)).into(),
+            body: body.into(),
+            missing,
+        });
+    }
+
+    fn set_preview_index(&mut self, index: usize, cx: &mut Context<Self>) {
+        self.preview_index = index;
+        self.refresh_preview(cx);
+        cx.notify();
+    }
+
+    fn add_attachments(&mut self, cx: &mut Context<Self>) {
+        let task: gpui::Task<Option<Vec<rfd::FileHandle>>> = cx
+            .background_executor()
+            .spawn(async move { rfd::AsyncFileDialog::new().pick_files().await });
+
+        cx.spawn(|weak_entity: WeakEntity<HomeView>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let Some(handles) = task.await else {
+                    return;
+                };
+                let results: Vec<_> = handles
+                    .iter()
+                    .map(|handle| Attachment::load(handle.path()))
+                    .collect();
+
+                if let Some(view) = weak_entity.upgrade() {
+                    view.update(&mut cx, |this, cx| {
+                        for result in results {
+                            match result {
+                                Ok(attachment) => {
+                                    if !this.attachments.iter().any(|a| a.path == attachment.path) {
+                                        this.attachments.push(attachment);
+                                    }
+                                }
+                                Err(e) => {
+                                    this.sending_state = SendingState::Error(format!("{:#}", e));
+                                }
+                            }
+                        }
+                        cx.notify();
+                    })
+                    .ok();
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn import_recipients(&mut self, cx: &mut Context<Self>) {
+        let task: gpui::Task<Option<rfd::FileHandle>> =
+            cx.background_executor().spawn(async move {
+                rfd::AsyncFileDialog::new()
+                    .add_filter("CSV/TSV Files", &["csv", "tsv", "txt"])
+                    .pick_file()
+                    .await
+            });
+
+        cx.spawn(|weak_entity: WeakEntity<HomeView>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                if let Some(file_handle) = task.await {
+                    let path = file_handle.path().to_path_buf();
+                    let result = RecipientTable::load(&path);
+
+                    if let Some(view) = weak_entity.upgrade() {
+                        view.update(&mut cx, |this, cx| {
+                            match result {
+                                Ok(table) => {
+                                    let mapping = table.default_mapping();
+                                    this.recipient_import = Some(RecipientImport {
+                                        path,
+                                        table,
+                                        mapping,
+                                    });
+                                    this.refresh_recipients(cx);
+                                }
+                                Err(e) => {
+                                    this.sending_state =
+                                        SendingState::Error(format!("导入收件人失败: {:#}", e));
+                                }
+                            }
+                            cx.notify();
+                        })
+                        .ok();
+                    }
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn set_email_column(&mut self, column: usize, cx: &mut Context<Self>) {
+        if let Some(import) = &mut self.recipient_import {
+            import.mapping.email_column = column;
+            import.mapping.variable_columns.remove(&column);
+            self.refresh_recipients(cx);
             cx.notify();
-            return;
         }
+    }
 
-        if subject.trim().is_empty() {
-            self.sending_state = SendingState::Error("请输入邮件主题".to_string());
+    fn toggle_variable_column(&mut self, column: usize, cx: &mut Context<Self>) {
+        if let Some(import) = &mut self.recipient_import {
+            if !import.mapping.variable_columns.remove(&column) {
+                import.mapping.variable_columns.insert(column);
+            }
+            self.refresh_recipients(cx);
             cx.notify();
-            return;
         }
+    }
+
+    /// 用发送记录中的主题和模板，准备只包含失败和未发送收件人的新发送。
+    pub fn load_retry(
+        &mut self,
+        record: &CampaignRecord,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let html_content = match record.load_template() {
+            Ok(content) => content,
+            Err(e) => {
+                self.sending_state = SendingState::Error(format!("{:#}", e));
+                cx.notify();
+                return;
+            }
+        };
+
+        self.selected_file = record.template_path.clone();
+        self.html_content = Some(html_content);
+        self.inline_css = record.inline_css;
+        self.attachments = record.attachments.clone();
+        self.text_input.update(cx, |input, cx| {
+            input.set_value(record.text_body.clone().unwrap_or_default(), window, cx)
+        });
+        self.personal_attachment_input.update(cx, |input, cx| {
+            input.set_value(
+                record.personal_attachment.clone().unwrap_or_default(),
+                window,
+                cx,
+            )
+        });
+        self.attachment_check = None;
+        self.preview_index = 0;
+        self.subject_input.update(cx, |input, cx| {
+            input.set_value(record.subject.clone(), window, cx)
+        });
+        for field in CopyField::ALL {
+            let value = match field {
+                CopyField::Cc => &record.cc,
+                CopyField::Bcc => &record.bcc,
+                CopyField::ReplyTo => &record.reply_to,
+            };
+            self.copy_input(field)
+                .update(cx, |input, cx| input.set_value(value.clone(), window, cx));
+        }
+        self.bcc_batch = record.bcc_batch.is_some();
+        if let Some(batch) = &record.bcc_batch {
+            self.batch_size_input.update(cx, |input, cx| {
+                input.set_value(batch.size.to_string(), window, cx)
+            });
+            self.batch_to_input.update(cx, |input, cx| {
+                input.set_value(batch.visible_to.clone(), window, cx)
+            });
+        }
+        self.retry = Some(RetrySource {
+            subject: record.subject.clone(),
+            recipients: record.retry_recipients(),
+            account: record.account.clone(),
+            account_warning: None,
+        });
+        let profiles = load_profiles(cx).unwrap_or_default();
+        match record.matching_profile(&profiles, &self.profile) {
+            Some(name) if name != self.profile => self.select_profile(name.to_string(), cx),
+            _ => self.check_retry_account(cx),
+        }
+        self.refresh_template(cx);
+        self.sending_state = SendingState::Idle;
+        self.send_progress = None;
+        cx.notify();
+    }
+
+    fn copy_input(&self, field: CopyField) -> &Entity<InputState> {
+        match field {
+            CopyField::Cc => &self.cc_input,
+            CopyField::Bcc => &self.bcc_input,
+            CopyField::ReplyTo => &self.reply_to_input,
+        }
+    }
+
+    /// 当前生效的收件人：重试列表优先，其次是导入的表格，最后是手动输入。
+    fn current_recipients(&self) -> &[Recipient] {
+        match &self.retry {
+            Some(retry) => &retry.recipients,
+            None => &self.recipient_list.recipients,
+        }
+    }
+
+    /// 按当前界面上的模板、主题、收件人和账号组装发送任务，尚未校验。
+    fn build_campaign(&self, cx: &mut Context<Self>) -> anyhow::Result<Campaign> {
+        let html_content = self.html_content.clone().context("请先选择 HTML 文件")?;
+
+        let subject = self.subject_input.read(cx).value().to_string();
+        let recipients = self.current_recipients().to_vec();
+
+        let config = load_profiles(cx)
+            .and_then(|p| p.config(Some(&self.profile)))
+            .context("加载配置失败")?;
+
+        let text_body = self.text_input.read(cx).value().to_string();
+        let personal_attachment = self.personal_attachment_input.read(cx).value().to_string();
+        let bcc_batch = if self.bcc_batch {
+            let size = self
+                .batch_size_input
+                .read(cx)
+                .value()
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|size| *size > 0)
+                .context("密送群发的每批人数需要是正整数")?;
+            Some(BccBatch {
+                size,
+                visible_to: self.batch_to_input.read(cx).value().to_string(),
+            })
+        } else {
+            None
+        };
+        let base_dir = self
+            .selected_file
+            .as_ref()
+            .and_then(|p| p.parent())
+            .map(|dir| dir.to_path_buf());
+
+        Ok(Campaign::new(config, recipients, subject, html_content)
+            .text_body(Some(text_body))
+            .attachments(self.attachments.clone())
+            .personal_attachment(Some(personal_attachment))
+            .base_dir(base_dir)
+            .cc(self.cc_input.read(cx).value().to_string())
+            .bcc(self.bcc_input.read(cx).value().to_string())
+            .reply_to(self.reply_to_input.read(cx).value().to_string())
+            .bcc_batch(bcc_batch)
+            .inline_css(self.inline_css)
+            .skip_invalid(self.skip_invalid))
+    }
+
+    /// 发送前检查每个收件人的个性化附件是否存在。
+    fn check_attachments(&mut self, cx: &mut Context<Self>) {
+        let result = self.build_campaign(cx).and_then(|campaign| {
+            if campaign.personal_attachment.is_none() {
+                anyhow::bail!("请先填写个性化附件路径");
+            }
+            campaign.check_attachments()
+        });
 
-        let config = match MailConfig::load() {
-            Ok(cfg) => cfg,
+        self.attachment_check = Some(match result {
+            Ok(count) => AttachmentCheck::AllFound(count),
+            Err(e) => AttachmentCheck::Failed(format!("{:#}", e)),
+        });
+        cx.notify();
+    }
+
+    /// 用第一个收件人的数据合并后，只发给测试地址。
+    fn send_test(&mut self, cx: &mut Context<Self>) {
+        let campaign = match self.build_campaign(cx).and_then(|c| c.test_copy()) {
+            Ok(campaign) => campaign,
             Err(e) => {
-                self.sending_state = SendingState::Error(format!("加载配置失败: {}", e));
+                self.sending_state = SendingState::Error(format!("{:#}", e));
                 cx.notify();
                 return;
             }
         };
+        if let Err(e) = campaign.validate() {
+            self.sending_state = SendingState::Error(e.to_string());
+            cx.notify();
+            return;
+        }
+
+        self.sending_test = true;
+        cx.notify();
+
+        let task = sender::runtime().spawn(async move { campaign.send().await });
 
-        if let Err(e) = config.validate() {
-            self.sending_state = SendingState::Error(format!("配置验证失败: {}", e));
+        cx.spawn(|view: WeakEntity<HomeView>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let result: anyhow::Result<CampaignReport> =
+                    task.await.map_err(anyhow::Error::from).and_then(|r| r);
+                view.update(&mut cx, |this, cx| {
+                    this.sending_test = false;
+                    this.sending_state = match result {
+                        Ok(report) if report.failed_count() == 0 => {
+                            let to: Vec<&str> =
+                                report.results.iter().map(|r| r.email.as_str()).collect();
+                            SendingState::Success(format!("测试邮件已发送到 {}", to.join(", ")))
+                        }
+                        Ok(report) => {
+                            SendingState::Error(format!("测试邮件发送失败: {}", report.summary()))
+                        }
+                        Err(e) => SendingState::Error(format!("测试邮件发送失败: {}", e)),
+                    };
+                    cx.notify();
+                })
+                .ok();
+            }
+        })
+        .detach();
+    }
+
+    fn send_email(&mut self, cx: &mut Context<Self>) {
+        let campaign = match self.build_campaign(cx) {
+            Ok(campaign) => campaign,
+            Err(e) => {
+                self.sending_state = SendingState::Error(format!("{:#}", e));
+                cx.notify();
+                return;
+            }
+        };
+        if let Err(e) = campaign.validate() {
+            self.sending_state = SendingState::Error(e.to_string());
             cx.notify();
             return;
         }
 
         self.sending_state = SendingState::Sending;
+        self.send_progress = Some(SendProgress::new(
+            campaign
+                .recipients
+                .iter()
+                .map(|r| r.email.clone())
+                .collect(),
+        ));
+        let control = SendControl::new();
+        self.send_control = Some(control.clone());
         cx.notify();
 
-        let task: gpui::Task<anyhow::Result<String>> = cx.background_executor().spawn(
-            Self::send_email_async(config, recipients_text, subject, html_content),
-        );
+        let template_path = self.selected_file.clone();
+        let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+        let task = sender::runtime().spawn(async move {
+            let report = campaign
+                .send_with_progress(&control, move |index, result| {
+                    progress_tx.send((index, result.clone())).ok();
+                })
+                .await?;
+            let record = CampaignRecord::new(&campaign, template_path, &report);
+            if let Err(e) = record.save() {
+                eprintln!("保存发送记录失败: {:?}", e);
+            }
+            Ok((report, record))
+        });
 
         cx.spawn(|view: WeakEntity<HomeView>, cx: &mut AsyncApp| {
             let mut cx = cx.clone();
             async move {
-                match task.await {
-                    Ok(success_msg) => {
+                while let Some((index, result)) = progress_rx.recv().await {
+                    view.update(&mut cx, |this, cx| {
+                        if let Some(progress) = &mut this.send_progress {
+                            progress.record(index, result);
+                        }
+                        cx.notify();
+                    })
+                    .ok();
+                }
+
+                let result: anyhow::Result<(CampaignReport, CampaignRecord)> =
+                    task.await.map_err(anyhow::Error::from).and_then(|r| r);
+                match result {
+                    Ok((report, record)) => {
                         view.update(&mut cx, |this, cx| {
-                            this.sending_state = SendingState::Success(success_msg);
+                            this.send_control = None;
+                            this.retry = None;
+                            this.last_retry_count = record.retry_count();
+                            this.last_record = Some(record);
+                            this.refresh_preview(cx);
+                            this.sending_state = if report.all_failed() {
+                                SendingState::Error(format!("发送失败: {}", report.summary()))
+                            } else {
+                                SendingState::Success(report.summary())
+                            };
                             cx.notify();
                         })
                         .ok();
                     }
                     Err(e) => {
                         view.update(&mut cx, |this, cx| {
+                            this.send_control = None;
                             this.sending_state = SendingState::Error(format!("发送失败: {}", e));
                             cx.notify();
                         })
@@ -166,75 +821,6 @@ impl HomeView {
         .detach();
     }
 
-    async fn send_email_async(
-        config: MailConfig,
-        recipients_text: String,
-        subject: String,
-        html_content: String,
-    ) -> anyhow::Result<String> {
-        use lettre::{
-            Message, SmtpTransport, Transport,
-            message::{Mailbox, header::ContentType},
-            transport::smtp::authentication::Credentials,
-        };
-
-        let recipients: Vec<String> = recipients_text
-            .lines()
-            .map(|line| line.trim())
-            .filter(|line| !line.is_empty())
-            .map(|s| s.to_string())
-            .collect();
-
-        if recipients.is_empty() {
-            anyhow::bail!("没有有效的收件人地址");
-        }
-
-        let from_mailbox: Mailbox = format!("{} <{}>", config.sender_name, config.email_address)
-            .parse()
-            .map_err(|e| anyhow::anyhow!("发件人地址格式错误: {}", e))?;
-
-        let creds = Credentials::new(config.email_address.clone(), config.password.clone());
-
-        let mailer = SmtpTransport::relay(&config.smtp_server)?
-            .port(config.smtp_port)
-            .credentials(creds)
-            .build();
-
-        let mut success_count = 0;
-        let mut failed_recipients = Vec::new();
-
-        for recipient in &recipients {
-            let to_mailbox: Mailbox = recipient
-                .parse()
-                .map_err(|e| anyhow::anyhow!("收件人地址 {} 格式错误: {}", recipient, e))?;
-
-            let email = Message::builder()
-                .from(from_mailbox.clone())
-                .to(to_mailbox)
-                .subject(&subject)
-                .header(ContentType::TEXT_HTML)
-                .body(html_content.clone())?;
-
-            match mailer.send(&email) {
-                Ok(_) => success_count += 1,
-                Err(e) => failed_recipients.push(format!("{}: {}", recipient, e)),
-            }
-        }
-
-        if failed_recipients.is_empty() {
-            Ok(format!("成功发送 {} 封邮件", success_count))
-        } else if success_count > 0 {
-            Ok(format!(
-                "部分成功: {} 封成功, {} 封失败\n失败列表:\n{}",
-                success_count,
-                failed_recipients.len(),
-                failed_recipients.join("\n")
-            ))
-        } else {
-            anyhow::bail!("全部失败:\n{}", failed_recipients.join("\n"))
-        }
-    }
-
     fn render_header(&self, cx: &mut Context<Self>) -> impl IntoElement {
         let view_handle = cx.entity();
         div()
@@ -250,14 +836,27 @@ impl HomeView {
                     .text_color(rgb(0xe4e4e7)),
             )
             .child(
-                Button::new("settings-btn")
-                    .icon(IconName::Settings)
-                    .on_click(move |_, _, cx| {
-                        println!("setting button clicked");
-                        view_handle.update(cx, |_, cx| {
-                            cx.emit(Events::ViewChanged(Views::SettingsView));
-                        })
-                    }),
+                div()
+                    .flex()
+                    .gap_2()
+                    .child(Button::new("history-btn").label("发送记录").on_click({
+                        let view_handle = view_handle.clone();
+                        move |_, _, cx| {
+                            view_handle.update(cx, |_, cx| {
+                                cx.emit(Events::ViewChanged(Views::HistoryView));
+                            })
+                        }
+                    }))
+                    .child(
+                        Button::new("settings-btn")
+                            .icon(IconName::Settings)
+                            .on_click(move |_, _, cx| {
+                                println!("setting button clicked");
+                                view_handle.update(cx, |_, cx| {
+                                    cx.emit(Events::ViewChanged(Views::SettingsView));
+                                })
+                            }),
+                    ),
             )
     }
 
@@ -271,6 +870,8 @@ impl HomeView {
             .unwrap_or_else(|| "未选择文件".to_string());
 
         let file_path = self.selected_file.as_ref().map(|p| p.display().to_string());
+        let images = self.image_check.total;
+        let missing_images = &self.image_check.missing;
 
         div()
             .flex()
@@ -310,6 +911,13 @@ impl HomeView {
                             .on_click(cx.listener(|view, _, _, cx| {
                                 view.select_file(cx);
                             })),
+                    )
+                    .child(
+                        Button::new("import-recipients-btn")
+                            .label("导入收件人")
+                            .on_click(cx.listener(|view, _, _, cx| {
+                                view.import_recipients(cx);
+                            })),
                     ),
             )
             .when_some(file_path, move |this, path| {
@@ -320,9 +928,323 @@ impl HomeView {
                         .child(format!("路径: {}", path)),
                 )
             })
+            .when(self.html_content.is_some(), |this| {
+                this.child(
+                    Checkbox::new("inline-css")
+                        .label("发送前将 <style> 中的 CSS 内联到各元素上")
+                        .checked(self.inline_css)
+                        .on_click(cx.listener(|view, checked: &bool, _, cx| {
+                            view.inline_css = *checked;
+                            view.refresh_template(cx);
+                            cx.notify();
+                        })),
+                )
+            })
+            .when_some(self.css_report.as_ref(), |this, report| {
+                let warnings = report.warnings();
+                this.child(div().text_xs().text_color(rgb(0x71717a)).child(format!(
+                    "已内联到 {} 个元素，{} 条 @media 等规则保留在 <style> 中",
+                    report.styled_elements, report.kept_at_rules
+                )))
+                .when(!warnings.is_empty(), |this| {
+                    this.child(
+                        div()
+                            .text_xs()
+                            .text_color(rgb(0xfbbf24))
+                            .child(warnings.join("\n")),
+                    )
+                })
+            })
+            .when(images > missing_images.len(), |this| {
+                this.child(div().text_xs().text_color(rgb(0x71717a)).child(format!(
+                    "将以内嵌方式附带 {} 张本地图片",
+                    images - missing_images.len()
+                )))
+            })
+            .when(!missing_images.is_empty(), |this| {
+                this.child(div().text_xs().text_color(rgb(0xfbbf24)).child(format!(
+                        "找不到以下 {} 张图片，发送时将保持原样:\n{}",
+                        missing_images.len(),
+                        missing_images
+                            .iter()
+                            .map(|i| format!("{} ({})", i.src, i.path.display()))
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    )))
+            })
+    }
+
+    fn render_attachment_section(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let rows = self.attachments.iter().enumerate().map(|(i, attachment)| {
+            div()
+                .flex()
+                .items_center()
+                .gap_3()
+                .child(
+                    div()
+                        .flex_1()
+                        .truncate()
+                        .text_sm()
+                        .text_color(rgb(0xe4e4e7))
+                        .child(attachment.file_name()),
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0x71717a))
+                        .child(attachment.content_type.clone()),
+                )
+                .child(
+                    div()
+                        .w(px(72.0))
+                        .text_xs()
+                        .text_color(rgb(0xa1a1aa))
+                        .child(attachments::format_size(attachment.size)),
+                )
+                .child(
+                    Button::new(("remove-attachment", i))
+                        .label("移除")
+                        .compact()
+                        .ghost()
+                        .on_click(cx.listener(move |view, _, _, cx| {
+                            view.attachments.remove(i);
+                            cx.notify();
+                        })),
+                )
+        });
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_3()
+            .p_4()
+            .bg(rgb(0x27272a))
+            .rounded_lg()
+            .child(
+                div()
+                    .flex()
+                    .justify_between()
+                    .items_center()
+                    .child(
+                        Label::new("附件")
+                            .text_color(rgb(0xe4e4e7))
+                            .font_semibold()
+                            .text_lg(),
+                    )
+                    .child(
+                        Button::new("add-attachment-btn")
+                            .label("添加附件")
+                            .on_click(cx.listener(|view, _, _, cx| {
+                                view.add_attachments(cx);
+                            })),
+                    ),
+            )
+            .when(self.attachments.is_empty(), |this| {
+                this.child(div().text_xs().text_color(rgb(0x71717a)).child("没有附件"))
+            })
+            .children(rows)
+            .when(!self.attachments.is_empty(), |this| {
+                this.child(div().text_xs().text_color(rgb(0x71717a)).child(format!(
+                    "共 {} 个附件，{}",
+                    self.attachments.len(),
+                    attachments::format_size(attachments::total_size(&self.attachments))
+                )))
+            })
+            .when_some(
+                attachments::size_warning(&self.attachments),
+                |this, warning| {
+                    this.child(div().text_xs().text_color(rgb(0xfbbf24)).child(warning))
+                },
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_2()
+                    .child(
+                        div()
+                            .text_sm()
+                            .font_semibold()
+                            .text_color(rgb(0xe4e4e7))
+                            .child("个性化附件"),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_3()
+                            .child(
+                                div()
+                                    .flex_1()
+                                    .child(Input::new(&self.personal_attachment_input)),
+                            )
+                            .child(
+                                Button::new("check-attachments-btn")
+                                    .label("检查附件")
+                                    .on_click(cx.listener(|view, _, _, cx| {
+                                        view.check_attachments(cx);
+                                    })),
+                            ),
+                    )
+                    .child(div().text_xs().text_color(rgb(0x71717a)).child(
+                        "每个收件人附加各自的文件，路径中的 {{字段}} 按收件人数据替换；\
+                         相对路径以 HTML 文件所在目录为基准。留空则不使用。",
+                    ))
+                    .when_some(self.attachment_check.as_ref(), |this, check| {
+                        let (message, color) = match check {
+                            AttachmentCheck::AllFound(count) => {
+                                (format!("{} 个收件人的附件均已找到", count), rgb(0x34d399))
+                            }
+                            AttachmentCheck::Failed(error) => (error.clone(), rgb(0xf87171)),
+                        };
+                        this.child(div().text_xs().text_color(color).child(message))
+                    }),
+            )
+    }
+
+    fn render_import_section(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let Some(import) = &self.recipient_import else {
+            return div();
+        };
+
+        let RecipientList {
+            recipients,
+            rejected,
+            invalid,
+        } = &self.recipient_list;
+        let file_name = import
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let columns = import.table.headers.iter().enumerate().map(|(i, header)| {
+            let is_email = import.mapping.email_column == i;
+            let is_variable = !is_email && import.mapping.variable_columns.contains(&i);
+
+            div()
+                .flex()
+                .items_center()
+                .gap_3()
+                .child(
+                    div()
+                        .flex_1()
+                        .text_sm()
+                        .text_color(rgb(0xe4e4e7))
+                        .child(header.clone()),
+                )
+                .child(
+                    div()
+                        .flex_1()
+                        .text_xs()
+                        .text_color(rgb(0x71717a))
+                        .child(import.table.sample(i).unwrap_or_default().to_string()),
+                )
+                .child(
+                    Button::new(("email-column", i))
+                        .label("邮箱")
+                        .compact()
+                        .selected(is_email)
+                        .on_click(cx.listener(move |view, _, _, cx| {
+                            view.set_email_column(i, cx);
+                        })),
+                )
+                .child(
+                    Button::new(("variable-column", i))
+                        .label("变量")
+                        .compact()
+                        .selected(is_variable)
+                        .disabled(is_email)
+                        .on_click(cx.listener(move |view, _, _, cx| {
+                            view.toggle_variable_column(i, cx);
+                        })),
+                )
+        });
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_3()
+            .p_4()
+            .bg(rgb(0x27272a))
+            .rounded_lg()
+            .child(
+                div()
+                    .flex()
+                    .justify_between()
+                    .items_center()
+                    .child(
+                        Label::new(format!("已导入: {}", file_name))
+                            .text_color(rgb(0xe4e4e7))
+                            .font_semibold(),
+                    )
+                    .child(
+                        Button::new("clear-import-btn")
+                            .label("清除导入")
+                            .ghost()
+                            .on_click(cx.listener(|view, _, _, cx| {
+                                view.recipient_import = None;
+                                view.refresh_recipients(cx);
+                                cx.notify();
+                            })),
+                    ),
+            )
+            .child(div().text_xs().text_color(rgb(0x71717a)).child(format!(
+                "共 {} 行{}，收件人 {} 个 (其中地址无效 {} 个)，拒绝 {} 行",
+                import.table.row_count(),
+                if import.table.has_header {
+                    " (已识别表头)"
+                } else {
+                    ""
+                },
+                recipients.len(),
+                invalid.len(),
+                rejected.len()
+            )))
+            .children(columns)
+            .when(!rejected.is_empty() || !invalid.is_empty(), |this| {
+                this.child(
+                    div()
+                        .id("rejected-rows")
+                        .flex()
+                        .flex_col()
+                        .max_h(px(160.0))
+                        .p_3()
+                        .bg(rgb(0x18181b))
+                        .rounded_md()
+                        .overflow_y_scroll()
+                        .children(rejected.iter().map(|row| {
+                            div()
+                                .text_xs()
+                                .text_color(rgb(0xf87171))
+                                .child(format!("第 {} 行: {}", row.line, row.reason))
+                        }))
+                        .children(invalid.iter().map(|item| {
+                            div().text_xs().text_color(rgb(0xfbbf24)).child(format!(
+                                "第 {} 行: 地址无效 {} ({})",
+                                item.line, item.email, item.reason
+                            ))
+                        })),
+                )
+            })
     }
 
-    fn render_email_info_section(&self) -> impl IntoElement {
+    fn render_email_info_section(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let rejected = match (&self.retry, &self.recipient_import) {
+            (None, None) => self.recipient_list.rejected.as_slice(),
+            _ => &[],
+        };
+        let profiles = self.profile_names.iter().enumerate().map(|(i, name)| {
+            let name = name.clone();
+            Button::new(("send-profile", i))
+                .label(name.clone())
+                .compact()
+                .selected(name == self.profile)
+                .on_click(cx.listener(move |view, _, _, cx| {
+                    view.select_profile(name.clone(), cx);
+                }))
+        });
+
         div()
             .flex()
             .flex_col()
@@ -337,6 +1259,20 @@ impl HomeView {
                     .text_color(rgb(0xe4e4e7))
                     .child("邮件信息"),
             )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_2()
+                    .child(
+                        div()
+                            .text_sm()
+                            .font_semibold()
+                            .text_color(rgb(0xe4e4e7))
+                            .child("发件账号"),
+                    )
+                    .child(div().flex().flex_wrap().gap_2().children(profiles)),
+            )
             .child(
                 div()
                     .flex()
@@ -351,6 +1287,26 @@ impl HomeView {
                     )
                     .child(Input::new(&self.subject_input)),
             )
+            .child(self.render_copy_inputs(cx))
+            .child(self.render_bcc_batch(cx))
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_2()
+                    .child(
+                        div()
+                            .text_sm()
+                            .font_semibold()
+                            .text_color(rgb(0xe4e4e7))
+                            .child("纯文本正文"),
+                    )
+                    .child(Input::new(&self.text_input))
+                    .child(div().text_xs().text_color(rgb(0x71717a)).child(
+                        "邮件同时包含 HTML 和纯文本两个版本，不显示 HTML 的客户端会显示纯文本版本。\
+                         可在预览的「文本」模式中查看。",
+                    )),
+            )
             .child(
                 div()
                     .flex()
@@ -364,13 +1320,282 @@ impl HomeView {
                             .child("收件人"),
                     )
                     .child(Input::new(&self.recipients_input))
+                    .child(div().text_xs().text_color(rgb(0x71717a)).child(
+                        "收件人列表 (每行一个邮箱地址；首行可为 email,name,company 形式的表头，\
+                                 主题和正文中的 {{name}} 等占位符将按收件人替换)",
+                    ))
+                    .children(rejected.iter().map(|row| {
+                        div()
+                            .text_xs()
+                            .text_color(rgb(0xf87171))
+                            .child(format!("第 {} 行: {}", row.line, row.reason))
+                    }))
+                    .child(
+                        Checkbox::new("skip-invalid")
+                            .label("跳过无效地址，继续发送其余收件人")
+                            .checked(self.skip_invalid)
+                            .on_click(cx.listener(|view, checked: &bool, _, cx| {
+                                view.skip_invalid = *checked;
+                                cx.notify();
+                            })),
+                    ),
+            )
+    }
+
+    fn render_copy_inputs(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let errors: Vec<String> = CopyField::ALL
+            .into_iter()
+            .filter_map(|field| {
+                let value = self.copy_input(field).read(cx).value().to_string();
+                let error = recipients::parse_addresses(&value).err()?;
+                Some(format!("{}地址无效: {}", field.label(), error))
+            })
+            .collect();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .child(
+                div()
+                    .text_sm()
+                    .font_semibold()
+                    .text_color(rgb(0xe4e4e7))
+                    .child("抄送、密送和回复地址"),
+            )
+            .children(CopyField::ALL.map(|field| {
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_2()
+                    .child(
+                        div()
+                            .w(px(64.))
+                            .text_sm()
+                            .text_color(rgb(0xa1a1aa))
+                            .child(field.label()),
+                    )
+                    .child(div().flex_1().child(Input::new(self.copy_input(field))))
+            }))
+            .children(
+                errors
+                    .into_iter()
+                    .map(|error| div().text_xs().text_color(rgb(0xf87171)).child(error)),
+            )
+            .child(div().text_xs().text_color(rgb(0x71717a)).child(
+                "收件人数据中的 cc、bcc、reply_to 列 (也可用 抄送、密送、回复地址) 非空时按人覆盖这里的设置；\
+                 同一列中的多个地址以分号分隔。",
+            ))
+    }
+
+    fn render_bcc_batch(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .child(
+                Checkbox::new("bcc-batch")
+                    .label("密送群发 (适合大型通知名单)")
+                    .checked(self.bcc_batch)
+                    .on_click(cx.listener(|view, checked: &bool, _, cx| {
+                        view.bcc_batch = *checked;
+                        cx.notify();
+                    })),
+            )
+            .when(self.bcc_batch, |this| {
+                this.child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_2()
+                        .child(
+                            div()
+                                .w(px(64.))
+                                .text_sm()
+                                .text_color(rgb(0xa1a1aa))
+                                .child("每批"),
+                        )
+                        .child(div().w(px(120.)).child(Input::new(&self.batch_size_input)))
+                        .child(
+                            div()
+                                .text_sm()
+                                .text_color(rgb(0xa1a1aa))
+                                .child("人，显示为"),
+                        )
+                        .child(div().flex_1().child(Input::new(&self.batch_to_input))),
+                )
+                .child(div().text_xs().text_color(rgb(0x71717a)).child(
+                    "每批收件人合并为一封邮件，全部放在密送中，信头 To 只显示上面的地址 (该地址不会收到邮件)。\
+                     整批收到同一封邮件，因此主题和正文中不能使用合并字段，也不能使用个性化附件和抄送、密送。",
+                ))
+            })
+    }
+
+    fn render_retry_section(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let Some(retry) = &self.retry else {
+            return div();
+        };
+
+        div()
+            .flex()
+            .justify_between()
+            .items_center()
+            .gap_3()
+            .p_4()
+            .bg(rgb(0x27272a))
+            .rounded_lg()
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_1()
+                    .child(
+                        Label::new(format!("重试 {} 个收件人", retry.recipients.len()))
+                            .text_color(rgb(0xe4e4e7))
+                            .font_semibold(),
+                    )
                     .child(
                         div()
                             .text_xs()
                             .text_color(rgb(0x71717a))
-                            .child("收件人列表 (每行一个邮箱地址)"),
+                            .child(format!("来自「{}」中失败或未发送的收件人", retry.subject)),
+                    )
+                    .when_some(retry.account_warning.clone(), |this, warning| {
+                        this.child(div().text_xs().text_color(rgb(0xfbbf24)).child(warning))
+                    }),
+            )
+            .child(
+                Button::new("clear-retry-btn")
+                    .label("取消重试")
+                    .ghost()
+                    .on_click(cx.listener(|view, _, _, cx| {
+                        view.retry = None;
+                        view.refresh_preview(cx);
+                        cx.notify();
+                    })),
+            )
+    }
+
+    fn render_preview_section(
+        &self,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let Some(preview) = &self.preview else {
+            return div();
+        };
+        let index = preview.index;
+
+        let mode_button = |id: &'static str, label: &'static str, mode: PreviewMode| {
+            Button::new(id)
+                .label(label)
+                .compact()
+                .selected(self.preview_mode == mode)
+                .on_click(cx.listener(move |view, _, _, cx| {
+                    view.preview_mode = mode;
+                    cx.notify();
+                }))
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_3()
+            .p_4()
+            .bg(rgb(0x27272a))
+            .rounded_lg()
+            .child(
+                div()
+                    .flex()
+                    .justify_between()
+                    .items_center()
+                    .child(
+                        div()
+                            .text_lg()
+                            .font_semibold()
+                            .text_color(rgb(0xe4e4e7))
+                            .child("预览"),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .gap_2()
+                            .child(mode_button("preview-text-btn", "文本", PreviewMode::Text))
+                            .child(mode_button(
+                                "preview-styled-btn",
+                                "样式",
+                                PreviewMode::Styled,
+                            )),
                     ),
             )
+            .when(preview.uses_fields, |this| {
+                this.child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_3()
+                        .child(
+                            Button::new("preview-prev-btn")
+                                .label("上一个")
+                                .compact()
+                                .disabled(index == 0)
+                                .on_click(cx.listener(move |view, _, _, cx| {
+                                    view.set_preview_index(index.saturating_sub(1), cx);
+                                })),
+                        )
+                        .child(
+                            div()
+                                .flex_1()
+                                .truncate()
+                                .text_sm()
+                                .text_color(rgb(0xa1a1aa))
+                                .child(preview.position.clone()),
+                        )
+                        .child(
+                            Button::new("preview-next-btn")
+                                .label("下一个")
+                                .compact()
+                                .disabled(index + 1 >= preview.count)
+                                .on_click(cx.listener(move |view, _, _, cx| {
+                                    view.set_preview_index(index + 1, cx);
+                                })),
+                        ),
+                )
+            })
+            .when(!preview.missing.is_empty(), |this| {
+                this.child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0xfbbf24))
+                        .child(preview.missing.clone()),
+                )
+            })
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(rgb(0xe4e4e7))
+                    .child(format!("主题: {}", preview.subject)),
+            )
+            .child(
+                div()
+                    .id("preview-body")
+                    .h(px(320.0))
+                    .p_3()
+                    .bg(rgb(0x18181b))
+                    .rounded_md()
+                    .overflow_y_scroll()
+                    .overflow_scrollbar()
+                    .map(|this| match self.preview_mode {
+                        PreviewMode::Text => this
+                            .text_sm()
+                            .text_color(rgb(0xe4e4e7))
+                            .child(preview.text.clone()),
+                        PreviewMode::Styled => this.child(
+                            TextView::html("preview-html", preview.body.clone(), window, cx)
+                                .selectable(true),
+                        ),
+                    }),
+            )
     }
 
     fn render_action_section(&self, cx: &mut Context<Self>) -> impl IntoElement {
@@ -382,20 +1607,105 @@ impl HomeView {
             .flex_col()
             .gap_4()
             .child(if is_sending {
+                let paused = self
+                    .send_control
+                    .as_ref()
+                    .is_some_and(|c| c.state() == RunState::Paused);
+                let cancelling = self
+                    .send_control
+                    .as_ref()
+                    .is_some_and(|c| c.state() == RunState::Cancelled);
+
                 div()
                     .flex()
                     .justify_center()
-                    .child(div().text_sm().text_color(rgb(0x71717a)).child("发送中..."))
+                    .items_center()
+                    .gap_3()
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(rgb(0x71717a))
+                            .child(if cancelling {
+                                "正在取消..."
+                            } else if paused {
+                                "已暂停"
+                            } else {
+                                "发送中..."
+                            }),
+                    )
+                    .child(
+                        Button::new("pause-btn")
+                            .label(if paused { "继续" } else { "暂停" })
+                            .disabled(cancelling)
+                            .on_click(cx.listener(move |view, _, _, cx| {
+                                if let Some(control) = &view.send_control {
+                                    if paused {
+                                        control.resume();
+                                    } else {
+                                        control.pause();
+                                    }
+                                }
+                                cx.notify();
+                            })),
+                    )
+                    .child(
+                        Button::new("cancel-btn")
+                            .label("取消")
+                            .danger()
+                            .disabled(cancelling)
+                            .on_click(cx.listener(|view, _, _, cx| {
+                                if let Some(control) = &view.send_control {
+                                    control.cancel();
+                                }
+                                cx.notify();
+                            })),
+                    )
             } else {
-                div().flex().justify_center().child(
-                    Button::new("send-btn")
-                        .label("发送邮件")
-                        .on_click(move |_, _, cx| {
-                            view_handle.update(cx, |this, cx| {
-                                this.send_email(cx);
-                            });
-                        }),
-                )
+                let retry_count = if self.retry.is_none() {
+                    self.last_retry_count
+                } else {
+                    0
+                };
+
+                div()
+                    .flex()
+                    .justify_center()
+                    .gap_3()
+                    .child(
+                        Button::new("send-test-btn")
+                            .label(if self.sending_test {
+                                "测试发送中..."
+                            } else {
+                                "发送测试"
+                            })
+                            .disabled(self.sending_test)
+                            .on_click(cx.listener(|view, _, _, cx| {
+                                view.send_test(cx);
+                            })),
+                    )
+                    .child(
+                        Button::new("send-btn")
+                            .label("发送邮件")
+                            .on_click(move |_, _, cx| {
+                                view_handle.update(cx, |this, cx| {
+                                    this.send_email(cx);
+                                });
+                            }),
+                    )
+                    .when(retry_count > 0, |this| {
+                        this.child(
+                            Button::new("retry-failed-btn")
+                                .label(format!("仅重试失败的 {} 个", retry_count))
+                                .on_click(cx.listener(|view, _, window, cx| {
+                                    if let Some(record) = view.last_record.clone() {
+                                        view.load_retry(&record, window, cx);
+                                    }
+                                })),
+                        )
+                    })
+            })
+            .when_some(self.send_progress.as_ref(), |this, progress| {
+                this.child(progress.render(cx))
             })
             .child(self.render_status_message())
     }
@@ -432,7 +1742,7 @@ impl EventEmitter<Events> for HomeView {}
 impl Render for HomeView {
     fn render(
         &mut self,
-        _window: &mut gpui::Window,
+        window: &mut gpui::Window,
         cx: &mut gpui::Context<Self>,
     ) -> impl gpui::IntoElement {
         div()
@@ -461,7 +1771,11 @@ impl Render for HomeView {
                                 .mx_auto()
                                 .w_full()
                                 .child(self.render_file_section(cx))
-                                .child(self.render_email_info_section())
+                                .child(self.render_attachment_section(cx))
+                                .child(self.render_import_section(cx))
+                                .child(self.render_retry_section(cx))
+                                .child(self.render_email_info_section(cx))
+                                .child(self.render_preview_section(window, cx))
                                 .child(self.render_action_section(cx)),
                         ),
                 ),
@@ -1,12 +1,20 @@
 pub mod app_view;
+pub mod history_view;
 pub mod home_view;
+pub mod send_progress;
 pub mod settings_view;
+pub mod unlock_view;
 
+use history_view::HistoryView;
 use home_view::HomeView;
 use settings_view::SettingsView;
+use unlock_view::UnlockView;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(clippy::enum_variant_names)]
 pub enum Views {
     HomeView,
     SettingsView,
+    HistoryView,
+    UnlockView,
 }
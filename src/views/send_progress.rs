@@ -0,0 +1,227 @@
+use std::ops::Range;
+
+use gpui::{
+    Context, Div, IntoElement, ParentElement, Rgba, Styled, div, prelude::FluentBuilder, px, rgb,
+    uniform_list,
+};
+use gpui_component::{StyledExt, progress::Progress};
+
+use batch_mail::sender::{RecipientResult, SendStatus};
+
+use crate::views::home_view::HomeView;
+
+/// 结果表中各状态的显示文字和颜色。
+pub fn status_style(status: &SendStatus) -> (&'static str, Rgba) {
+    match status {
+        SendStatus::Sent { .. } => ("成功", rgb(0x34d399)),
+        SendStatus::Failed { .. } => ("失败", rgb(0xf87171)),
+        SendStatus::Skipped { .. } => ("已跳过", rgb(0xa78bfa)),
+        SendStatus::Queued => ("已排队", rgb(0x60a5fa)),
+        SendStatus::Cancelled => ("已取消", rgb(0xfbbf24)),
+    }
+}
+
+/// 一次发送任务中每个收件人的实时状态，供首页的进度条和结果表使用。
+pub struct SendProgress {
+    emails: Vec<String>,
+    results: Vec<Option<RecipientResult>>,
+}
+
+impl SendProgress {
+    pub fn new(emails: Vec<String>) -> Self {
+        let results = vec![None; emails.len()];
+        Self { emails, results }
+    }
+
+    pub fn record(&mut self, index: usize, result: RecipientResult) {
+        if let Some(slot) = self.results.get_mut(index) {
+            *slot = Some(result);
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.emails.len()
+    }
+
+    pub fn sent_count(&self) -> usize {
+        self.results
+            .iter()
+            .flatten()
+            .filter(|r| matches!(r.status, SendStatus::Sent { .. }))
+            .count()
+    }
+
+    pub fn failed_count(&self) -> usize {
+        self.results
+            .iter()
+            .flatten()
+            .filter(|r| matches!(r.status, SendStatus::Failed { .. }))
+            .count()
+    }
+
+    pub fn skipped_count(&self) -> usize {
+        self.results
+            .iter()
+            .flatten()
+            .filter(|r| matches!(r.status, SendStatus::Skipped { .. }))
+            .count()
+    }
+
+    pub fn queued_count(&self) -> usize {
+        self.results
+            .iter()
+            .flatten()
+            .filter(|r| r.status == SendStatus::Queued)
+            .count()
+    }
+
+    pub fn cancelled_count(&self) -> usize {
+        self.results
+            .iter()
+            .flatten()
+            .filter(|r| r.status == SendStatus::Cancelled)
+            .count()
+    }
+
+    pub fn remaining_count(&self) -> usize {
+        self.results.iter().filter(|r| r.is_none()).count()
+    }
+
+    fn render_rows(&self, range: Range<usize>) -> Vec<Div> {
+        range
+            .filter_map(|i| {
+                let email = self.emails.get(i)?.clone();
+                let (status, detail, elapsed, color) = match &self.results[i] {
+                    None => ("等待中", String::new(), String::new(), rgb(0x71717a)),
+                    Some(result) => {
+                        let (label, color) = status_style(&result.status);
+                        let elapsed = match result.status {
+                            SendStatus::Sent { .. } | SendStatus::Failed { .. } => {
+                                format!("{:.1}s", result.elapsed.as_secs_f64())
+                            }
+                            _ => String::new(),
+                        };
+                        (label, result.status.detail(), elapsed, color)
+                    }
+                };
+
+                Some(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_3()
+                        .h(px(28.0))
+                        .px_3()
+                        .text_xs()
+                        .border_b_1()
+                        .border_color(rgb(0x27272a))
+                        .child(
+                            div()
+                                .w(px(200.0))
+                                .truncate()
+                                .text_color(rgb(0xe4e4e7))
+                                .child(email),
+                        )
+                        .child(div().w(px(48.0)).text_color(color).child(status))
+                        .child(
+                            div()
+                                .flex_1()
+                                .truncate()
+                                .text_color(rgb(0xa1a1aa))
+                                .child(detail),
+                        )
+                        .child(div().w(px(48.0)).text_color(rgb(0x71717a)).child(elapsed)),
+                )
+            })
+            .collect()
+    }
+
+    pub fn render(&self, cx: &mut Context<HomeView>) -> impl IntoElement {
+        let total = self.total();
+        let done = total - self.remaining_count();
+        let percent = if total == 0 {
+            0.0
+        } else {
+            done as f32 / total as f32 * 100.0
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_3()
+            .p_4()
+            .bg(rgb(0x27272a))
+            .rounded_lg()
+            .child(
+                div()
+                    .flex()
+                    .justify_between()
+                    .text_sm()
+                    .child(
+                        div()
+                            .font_semibold()
+                            .text_color(rgb(0xe4e4e7))
+                            .child(format!("发送进度 {}/{}", done, total)),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .gap_3()
+                            .text_xs()
+                            .child(
+                                div()
+                                    .text_color(rgb(0x34d399))
+                                    .child(format!("成功 {}", self.sent_count())),
+                            )
+                            .child(
+                                div()
+                                    .text_color(rgb(0xf87171))
+                                    .child(format!("失败 {}", self.failed_count())),
+                            )
+                            .when(self.skipped_count() > 0, |this| {
+                                this.child(
+                                    div()
+                                        .text_color(rgb(0xa78bfa))
+                                        .child(format!("跳过 {}", self.skipped_count())),
+                                )
+                            })
+                            .when(self.queued_count() > 0, |this| {
+                                this.child(
+                                    div()
+                                        .text_color(rgb(0x60a5fa))
+                                        .child(format!("排队 {}", self.queued_count())),
+                                )
+                            })
+                            .when(self.cancelled_count() > 0, |this| {
+                                this.child(
+                                    div()
+                                        .text_color(rgb(0xfbbf24))
+                                        .child(format!("取消 {}", self.cancelled_count())),
+                                )
+                            })
+                            .child(
+                                div()
+                                    .text_color(rgb(0x71717a))
+                                    .child(format!("剩余 {}", self.remaining_count())),
+                            ),
+                    ),
+            )
+            .child(Progress::new().value(percent))
+            .when(total > 0, |this| {
+                this.child(
+                    div().h(px(280.0)).bg(rgb(0x18181b)).rounded_md().child(
+                        uniform_list(
+                            "send-results",
+                            total,
+                            cx.processor(|view: &mut HomeView, range, _, _| {
+                                view.send_progress()
+                                    .map(|progress| progress.render_rows(range))
+                                    .unwrap_or_default()
+                            }),
+                        )
+                        .size_full(),
+                    ),
+                )
+            })
+    }
+}
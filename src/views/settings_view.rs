@@ -1,29 +1,78 @@
+use std::str::FromStr;
+
 use gpui::{
-    AppContext, Context, Entity, EventEmitter, InteractiveElement, IntoElement, ParentElement,
-    Render, StatefulInteractiveElement, Styled, Window, div, rgb,
+    AppContext, AsyncApp, Context, Entity, EventEmitter, InteractiveElement, IntoElement,
+    ParentElement, Render, StatefulInteractiveElement, Styled, WeakEntity, Window, div,
+    prelude::FluentBuilder, rgb,
 };
 use gpui_component::{
-    StyledExt,
-    button::Button,
+    Disableable, Selectable, StyledExt,
+    button::{Button, ButtonVariants},
     input::{Input, InputState},
     label::Label,
     scroll::ScrollableElement,
 };
 
-use crate::{events::Events, mail_config::MailConfig};
+use batch_mail::{
+    connection_test::{self, ConnectionReport},
+    mail_config::{MailConfig, Profile, Profiles, TlsMode},
+    sender,
+};
+
+use crate::{
+    events::Events,
+    views::unlock_view::{load_profiles, save_profiles},
+};
+
+/// 解析表单中的数字字段，留空时取默认值。
+fn parse_number<T: FromStr>(label: &str, value: &str, default: T) -> anyhow::Result<T> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(default);
+    }
+    value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("{}不是有效的数字: {}", label, value))
+}
+
+enum ConnectionTest {
+    Running,
+    Done(ConnectionReport),
+}
 
 pub struct SettingsView {
-    config: MailConfig,
+    profiles: Profiles,
+    /// 正在编辑的账号下标。
+    editing: usize,
+    error: Option<String>,
+    connection_test: Option<ConnectionTest>,
+    profile_name: Entity<InputState>,
     smtp_server: Entity<InputState>,
     smtp_port: Entity<InputState>,
+    tls_mode: TlsMode,
     email_address: Entity<InputState>,
     password: Entity<InputState>,
     sender_name: Entity<InputState>,
+    max_concurrency: Entity<InputState>,
+    max_per_second: Entity<InputState>,
+    max_per_minute: Entity<InputState>,
+    daily_cap: Entity<InputState>,
+    delay_ms: Entity<InputState>,
+    max_retries: Entity<InputState>,
+    retry_base_delay_ms: Entity<InputState>,
+    test_addresses: Entity<InputState>,
 }
 
 impl SettingsView {
     pub fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
-        let config = MailConfig::load().unwrap_or_default();
+        let profiles = load_profiles(cx).unwrap_or_default();
+        let editing = profiles.active_index();
+        let config = profiles.profiles[editing].config.clone();
+        let profile_name = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("账号名称，如 support、newsletter")
+                .default_value(&profiles.profiles[editing].name)
+        });
         let smtp_server = cx.new(|cx| {
             InputState::new(window, cx)
                 .placeholder("SMTP 服务器地址")
@@ -32,7 +81,7 @@ impl SettingsView {
         let smtp_port = cx.new(|cx| {
             InputState::new(window, cx)
                 .placeholder("SMTP 端口")
-                .default_value(&config.smtp_port.to_string())
+                .default_value(config.smtp_port.to_string())
         });
         let emil_address = cx.new(|cx| {
             InputState::new(window, cx)
@@ -42,6 +91,7 @@ impl SettingsView {
         let password = cx.new(|cx| {
             InputState::new(window, cx)
                 .placeholder("邮箱密码")
+                .masked(true)
                 .default_value(&config.password)
         });
         let sender_name = cx.new(|cx| {
@@ -49,35 +99,448 @@ impl SettingsView {
                 .placeholder("发件人名称")
                 .default_value(&config.sender_name)
         });
+        let max_concurrency = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("并发连接数")
+                .default_value(config.max_concurrency.to_string())
+        });
+        let max_per_second = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("每秒最多收件人数 (0 为不限)")
+                .default_value(config.max_per_second.to_string())
+        });
+        let max_per_minute = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("每分钟最多收件人数 (0 为不限)")
+                .default_value(config.max_per_minute.to_string())
+        });
+        let daily_cap = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("每日收件人上限 (0 为不限)")
+                .default_value(config.daily_cap.to_string())
+        });
+        let delay_ms = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("每封邮件间隔 (毫秒)")
+                .default_value(config.delay_ms.to_string())
+        });
+        let max_retries = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("临时错误最多重试次数")
+                .default_value(config.max_retries.to_string())
+        });
+        let retry_base_delay_ms = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("首次重试等待 (毫秒)，之后翻倍")
+                .default_value(config.retry_base_delay_ms.to_string())
+        });
+        let test_addresses = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("多个地址用逗号分隔，留空则发给自己")
+                .default_value(config.test_addresses.join(", "))
+        });
         Self {
-            config,
+            profiles,
+            editing,
+            error: None,
+            connection_test: None,
+            profile_name,
             smtp_server,
             smtp_port,
+            tls_mode: config.tls_mode,
             email_address: emil_address,
             password,
             sender_name,
+            max_concurrency,
+            max_per_second,
+            max_per_minute,
+            daily_cap,
+            delay_ms,
+            max_retries,
+            retry_base_delay_ms,
+            test_addresses,
         }
     }
 
-    fn save_config(&mut self, cx: &mut Context<Self>) {
+    /// 重新读取配置文件，丢弃未保存的修改。
+    pub fn reload(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.profiles = load_profiles(cx).unwrap_or_default();
+        self.editing = self.profiles.active_index();
+        self.error = None;
+        self.fill_form(window, cx);
+    }
+
+    /// 读取表单内容；数字字段填写有误时报错，而不是悄悄换成默认值。
+    fn read_form(&self, cx: &mut Context<Self>) -> anyhow::Result<Profile> {
         let smtp_server = self.smtp_server.read(cx).value();
         let smtp_port = self.smtp_port.read(cx).value();
         let emil_address = self.email_address.read(cx).value();
         let password = self.password.read(cx).value();
         let sender_name = self.sender_name.read(cx).value();
+        let max_concurrency = self.max_concurrency.read(cx).value();
+        let max_per_second = self.max_per_second.read(cx).value();
+        let max_per_minute = self.max_per_minute.read(cx).value();
+        let daily_cap = self.daily_cap.read(cx).value();
+        let delay_ms = self.delay_ms.read(cx).value();
+        let max_retries = self.max_retries.read(cx).value();
+        let retry_base_delay_ms = self.retry_base_delay_ms.read(cx).value();
+        let test_addresses = self.test_addresses.read(cx).value();
+
+        let max_concurrency = parse_number("并发连接数", &max_concurrency, 4)?;
+        if max_concurrency == 0 {
+            anyhow::bail!("并发连接数至少为 1");
+        }
+
+        Ok(Profile {
+            name: self.profile_name.read(cx).value().trim().to_string(),
+            config: MailConfig {
+                smtp_server: smtp_server.to_string(),
+                smtp_port: parse_number("SMTP 端口", &smtp_port, self.tls_mode.default_port())?,
+                tls_mode: self.tls_mode,
+                email_address: emil_address.to_string(),
+                password: password.to_string(),
+                sender_name: sender_name.to_string(),
+                max_concurrency,
+                max_per_second: parse_number("每秒最多收件人数", &max_per_second, 0)?,
+                max_per_minute: parse_number("每分钟最多收件人数", &max_per_minute, 0)?,
+                daily_cap: parse_number("每日收件人上限", &daily_cap, 0)?,
+                delay_ms: parse_number("发送间隔", &delay_ms, 0)?,
+                max_retries: parse_number("最大重试次数", &max_retries, 3)?,
+                retry_base_delay_ms: parse_number("重试初始间隔", &retry_base_delay_ms, 2000)?,
+                test_addresses: test_addresses
+                    .split([',', ';', '\n'])
+                    .map(|a| a.trim().to_string())
+                    .filter(|a| !a.is_empty())
+                    .collect(),
+            },
+        })
+    }
+
+    fn fill_form(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let profile = self.profiles.profiles[self.editing].clone();
+        let config = &profile.config;
+        self.tls_mode = config.tls_mode;
+        self.connection_test = None;
+        let fields = [
+            (&self.profile_name, profile.name.clone()),
+            (&self.smtp_server, config.smtp_server.clone()),
+            (&self.smtp_port, config.smtp_port.to_string()),
+            (&self.email_address, config.email_address.clone()),
+            (&self.password, config.password.clone()),
+            (&self.sender_name, config.sender_name.clone()),
+            (&self.max_concurrency, config.max_concurrency.to_string()),
+            (&self.max_per_second, config.max_per_second.to_string()),
+            (&self.max_per_minute, config.max_per_minute.to_string()),
+            (&self.daily_cap, config.daily_cap.to_string()),
+            (&self.delay_ms, config.delay_ms.to_string()),
+            (&self.max_retries, config.max_retries.to_string()),
+            (
+                &self.retry_base_delay_ms,
+                config.retry_base_delay_ms.to_string(),
+            ),
+            (&self.test_addresses, config.test_addresses.join(", ")),
+        ];
+        for (input, value) in fields {
+            input.update(cx, |input, cx| input.set_value(value, window, cx));
+        }
+        cx.notify();
+    }
+
+    /// 把表单内容写回正在编辑的账号 (仅内存)，选用中的账号改名时同步更新。
+    fn store_form(&mut self, cx: &mut Context<Self>) -> anyhow::Result<()> {
+        let profile = self.read_form(cx)?;
+        let editing = &mut self.profiles.profiles[self.editing];
+        if self.profiles.active == editing.name {
+            self.profiles.active = profile.name.clone();
+        }
+        *editing = profile;
+        Ok(())
+    }
+
+    /// 写回表单内容，出错时显示错误并返回 `false`，此时不应切换到其他账号。
+    fn try_store_form(&mut self, cx: &mut Context<Self>) -> bool {
+        match self.store_form(cx) {
+            Ok(()) => true,
+            Err(e) => {
+                self.error = Some(format!("{:#}", e));
+                cx.notify();
+                false
+            }
+        }
+    }
 
-        self.config = MailConfig {
-            smtp_server: smtp_server.to_string(),
-            smtp_port: smtp_port.parse().unwrap_or(587),
-            email_address: emil_address.to_string(),
-            password: password.to_string(),
-            sender_name: sender_name.to_string(),
+    fn select_profile(&mut self, index: usize, window: &mut Window, cx: &mut Context<Self>) {
+        if !self.try_store_form(cx) {
+            return;
+        }
+        self.editing = index;
+        self.error = None;
+        self.fill_form(window, cx);
+    }
+
+    fn add_profile(&mut self, duplicate: bool, window: &mut Window, cx: &mut Context<Self>) {
+        if !self.try_store_form(cx) {
+            return;
+        }
+        let profile = if duplicate {
+            let current = &self.profiles.profiles[self.editing];
+            Profile {
+                name: self.profiles.unique_name(&format!("{} 副本", current.name)),
+                config: current.config.clone(),
+            }
+        } else {
+            Profile {
+                name: self.profiles.unique_name("新账号"),
+                config: MailConfig::default(),
+            }
         };
+        self.profiles.profiles.push(profile);
+        self.editing = self.profiles.profiles.len() - 1;
+        self.error = None;
+        self.fill_form(window, cx);
+    }
+
+    fn delete_profile(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.profiles.profiles.len() <= 1 {
+            self.error = Some("至少需要保留一个账号".to_string());
+            cx.notify();
+            return;
+        }
+        let removed = self.profiles.profiles.remove(self.editing);
+        if self.profiles.active == removed.name {
+            self.profiles.active = self.profiles.profiles[0].name.clone();
+        }
+        self.editing = self.editing.min(self.profiles.profiles.len() - 1);
+        self.error = None;
+        self.fill_form(window, cx);
+    }
 
-        match self.config.save() {
-            Ok(_) => eprintln!("配置保存成功"),
-            Err(e) => eprintln!("配置保存失败: {:?}", e),
+    /// 切换加密方式；端口仍是原方式的常用端口时一并改为新方式的常用端口。
+    fn set_tls_mode(&mut self, mode: TlsMode, window: &mut Window, cx: &mut Context<Self>) {
+        let port = self.smtp_port.read(cx).value().to_string();
+        if port.is_empty() || port == self.tls_mode.default_port().to_string() {
+            self.smtp_port.update(cx, |input, cx| {
+                input.set_value(mode.default_port().to_string(), window, cx)
+            });
         }
+        self.tls_mode = mode;
+        cx.notify();
+    }
+
+    fn render_tls_field(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let modes = TlsMode::ALL.into_iter().enumerate().map(|(i, mode)| {
+            Button::new(("tls-mode", i))
+                .label(mode.label())
+                .compact()
+                .selected(mode == self.tls_mode)
+                .on_click(cx.listener(move |view, _, window, cx| {
+                    view.set_tls_mode(mode, window, cx);
+                }))
+        });
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .child(
+                div()
+                    .text_sm()
+                    .font_semibold()
+                    .text_color(rgb(0xe4e4e7))
+                    .child("加密方式"),
+            )
+            .child(div().flex().flex_wrap().gap_2().children(modes))
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(rgb(0x71717a))
+                    .child(format!("常用端口: {}", self.tls_mode.default_port())),
+            )
+    }
+
+    /// 用表单中当前填写的内容测试连接，不需要先保存。
+    fn test_connection(&mut self, cx: &mut Context<Self>) {
+        let config = match self.read_form(cx) {
+            Ok(profile) => profile.config,
+            Err(e) => {
+                self.error = Some(format!("{:#}", e));
+                cx.notify();
+                return;
+            }
+        };
+        self.error = None;
+        self.connection_test = Some(ConnectionTest::Running);
+        cx.notify();
+
+        let task =
+            sender::runtime().spawn(async move { connection_test::test_connection(&config).await });
+
+        cx.spawn(|view: WeakEntity<SettingsView>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let report = task.await.unwrap_or_else(|e| ConnectionReport {
+                    error: Some(format!("测试任务失败: {}", e)),
+                    ..Default::default()
+                });
+                view.update(&mut cx, |this, cx| {
+                    this.connection_test = Some(ConnectionTest::Done(report));
+                    cx.notify();
+                })
+                .ok();
+            }
+        })
+        .detach();
+    }
+
+    fn render_connection_test(&self) -> impl IntoElement {
+        let Some(test) = &self.connection_test else {
+            return div();
+        };
+
+        let container = div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .p_4()
+            .bg(rgb(0x27272a))
+            .rounded_lg()
+            .text_xs();
+
+        let report = match test {
+            ConnectionTest::Running => {
+                return container.child(
+                    div()
+                        .text_sm()
+                        .text_color(rgb(0x60a5fa))
+                        .child("正在测试连接..."),
+                );
+            }
+            ConnectionTest::Done(report) => report,
+        };
+
+        let info_row = |label: &'static str, value: String| {
+            div()
+                .flex()
+                .gap_3()
+                .child(div().w_20().text_color(rgb(0x71717a)).child(label))
+                .child(div().flex_1().text_color(rgb(0xe4e4e7)).child(value))
+        };
+
+        container
+            .child(match &report.error {
+                None => div()
+                    .text_sm()
+                    .font_semibold()
+                    .text_color(rgb(0x34d399))
+                    .child("连接测试成功"),
+                Some(error) => div()
+                    .text_sm()
+                    .font_semibold()
+                    .text_color(rgb(0xf87171))
+                    .child(error.clone()),
+            })
+            .when(!report.greeting.is_empty(), |this| {
+                this.child(info_row("问候", report.greeting.join("\n")))
+            })
+            .when(!report.server_name.is_empty(), |this| {
+                this.child(info_row("EHLO 主机名", report.server_name.clone()))
+                    .child(info_row(
+                        "加密",
+                        if report.encrypted {
+                            "已加密".to_string()
+                        } else {
+                            "未加密".to_string()
+                        },
+                    ))
+            })
+            .when(!report.ehlo_lines.is_empty(), |this| {
+                let mechanisms = if report.auth_mechanisms.is_empty() {
+                    "未声明".to_string()
+                } else {
+                    report.auth_mechanisms.join(" ")
+                };
+                this.child(info_row("AUTH 机制", mechanisms))
+                    .child(info_row("EHLO 响应", report.ehlo_lines.join("\n")))
+            })
+            .when_some(report.auth_response.clone(), |this, response| {
+                this.child(info_row("认证", response))
+            })
+    }
+
+    fn save_config(&mut self, cx: &mut Context<Self>) -> anyhow::Result<()> {
+        self.store_form(cx)?;
+        self.profiles.validate()?;
+        save_profiles(&self.profiles, cx)
+    }
+
+    fn render_profile_section(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let profiles = self
+            .profiles
+            .profiles
+            .iter()
+            .enumerate()
+            .map(|(i, profile)| {
+                let label = if profile.name == self.profiles.active {
+                    format!("{} (当前)", profile.name)
+                } else {
+                    profile.name.clone()
+                };
+                Button::new(("profile", i))
+                    .label(label)
+                    .compact()
+                    .selected(i == self.editing)
+                    .on_click(cx.listener(move |view, _, window, cx| {
+                        view.select_profile(i, window, cx);
+                    }))
+            });
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_3()
+            .p_4()
+            .bg(rgb(0x27272a))
+            .rounded_lg()
+            .child(
+                div()
+                    .text_sm()
+                    .font_semibold()
+                    .text_color(rgb(0xe4e4e7))
+                    .child("账号"),
+            )
+            .child(div().flex().flex_wrap().gap_2().children(profiles))
+            .child(
+                div()
+                    .flex()
+                    .gap_2()
+                    .child(
+                        Button::new("add-profile-btn")
+                            .label("新建")
+                            .compact()
+                            .on_click(cx.listener(|view, _, window, cx| {
+                                view.add_profile(false, window, cx);
+                            })),
+                    )
+                    .child(
+                        Button::new("duplicate-profile-btn")
+                            .label("复制")
+                            .compact()
+                            .on_click(cx.listener(|view, _, window, cx| {
+                                view.add_profile(true, window, cx);
+                            })),
+                    )
+                    .child(
+                        Button::new("delete-profile-btn")
+                            .label("删除")
+                            .compact()
+                            .danger()
+                            .disabled(self.profiles.profiles.len() <= 1)
+                            .on_click(cx.listener(|view, _, window, cx| {
+                                view.delete_profile(window, cx);
+                            })),
+                    ),
+            )
+            .child(self.render_form_field("账号名称", &self.profile_name))
     }
 
     fn render_form_field(
@@ -142,20 +605,55 @@ impl Render for SettingsView {
                     .overflow_y_scroll()
                     .overflow_scrollbar()
                     .flex_1()
+                    .child(self.render_profile_section(cx))
                     .child(self.render_form_field("SMTP 服务器", &self.smtp_server))
                     .child(self.render_form_field("SMTP 端口", &self.smtp_port))
+                    .child(self.render_tls_field(cx))
                     .child(self.render_form_field("邮箱地址", &self.email_address))
                     .child(self.render_form_field("邮箱密码", &self.password))
                     .child(self.render_form_field("发件人名称", &self.sender_name))
+                    .child(self.render_form_field("并发连接数", &self.max_concurrency))
+                    .child(self.render_form_field("每秒最多收件人数", &self.max_per_second))
+                    .child(self.render_form_field("每分钟最多收件人数", &self.max_per_minute))
+                    .child(self.render_form_field("每日收件人上限", &self.daily_cap))
+                    .child(self.render_form_field("发送间隔 (毫秒)", &self.delay_ms))
+                    .child(self.render_form_field("最大重试次数", &self.max_retries))
+                    .child(self.render_form_field("重试初始间隔 (毫秒)", &self.retry_base_delay_ms))
+                    .child(self.render_form_field("测试收件人", &self.test_addresses))
+                    .when_some(self.error.clone(), |this, error| {
+                        this.child(div().text_sm().text_color(rgb(0xf87171)).child(error))
+                    })
+                    .child(self.render_connection_test())
                     .child(
                         div()
                             .mt_4()
+                            .flex()
+                            .gap_3()
+                            .child(
+                                Button::new("test-connection-btn")
+                                    .label("测试连接")
+                                    .disabled(matches!(
+                                        self.connection_test,
+                                        Some(ConnectionTest::Running)
+                                    ))
+                                    .on_click(cx.listener(|view, _, _, cx| {
+                                        view.test_connection(cx);
+                                    })),
+                            )
                             .child(Button::new("confirm-btn").label("确定").on_click(
                                 move |_, _, cx| {
-                                    view_handle.update(cx, |this, cx| {
-                                        this.save_config(cx);
-                                        cx.emit(Events::ViewChanged(crate::views::Views::HomeView));
-                                        eprintln!("配置已经保存");
+                                    view_handle.update(cx, |this, cx| match this.save_config(cx) {
+                                        Ok(()) => {
+                                            this.error = None;
+                                            cx.emit(Events::ViewChanged(
+                                                crate::views::Views::HomeView,
+                                            ));
+                                            eprintln!("配置已经保存");
+                                        }
+                                        Err(e) => {
+                                            this.error = Some(format!("保存配置失败: {:#}", e));
+                                            cx.notify();
+                                        }
                                     });
                                 },
                             )),
@@ -0,0 +1,167 @@
+use gpui::{
+    App, AppContext, AsyncApp, BorrowAppContext, Context, Entity, EventEmitter, Global,
+    ParentElement, Render, Styled, WeakEntity, Window, div, prelude::FluentBuilder, px, rgb,
+};
+use gpui_component::{
+    Disableable, StyledExt,
+    button::Button,
+    input::{Input, InputState},
+    label::Label,
+};
+
+use batch_mail::{mail_config::Profiles, vault::Vault};
+
+use crate::{events::Events, views::Views};
+
+/// 已解锁的密码库，解锁后在整个应用中共享。
+pub struct UnlockedVault(pub Vault);
+
+impl Global for UnlockedVault {}
+
+/// 读取配置并填入密码库中的账号密码。
+pub fn load_profiles(cx: &mut App) -> anyhow::Result<Profiles> {
+    if !cx.has_global::<UnlockedVault>() {
+        anyhow::bail!("密码库尚未解锁");
+    }
+    cx.update_global::<UnlockedVault, _>(|vault, _| Profiles::load_unlocked(&mut vault.0))
+}
+
+/// 保存配置，并把各账号密码写入密码库。
+pub fn save_profiles(profiles: &Profiles, cx: &mut App) -> anyhow::Result<()> {
+    if !cx.has_global::<UnlockedVault>() {
+        anyhow::bail!("密码库尚未解锁");
+    }
+    cx.update_global::<UnlockedVault, _>(|vault, _| profiles.save_with_vault(&mut vault.0))
+}
+
+/// 启动时输入主密码解锁密码库；首次使用时设置主密码。
+pub struct UnlockView {
+    passphrase: Entity<InputState>,
+    confirm: Entity<InputState>,
+    creating: bool,
+    unlocking: bool,
+    error: Option<String>,
+}
+
+impl UnlockView {
+    pub fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let passphrase = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("主密码")
+                .masked(true)
+        });
+        let confirm = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("再次输入主密码")
+                .masked(true)
+        });
+
+        Self {
+            passphrase,
+            confirm,
+            creating: !Vault::exists(),
+            unlocking: false,
+            error: None,
+        }
+    }
+
+    fn unlock(&mut self, cx: &mut Context<Self>) {
+        let passphrase = self.passphrase.read(cx).value().to_string();
+        if self.creating && passphrase != self.confirm.read(cx).value().as_ref() {
+            self.error = Some("两次输入的主密码不一致".to_string());
+            cx.notify();
+            return;
+        }
+
+        self.unlocking = true;
+        self.error = None;
+        cx.notify();
+
+        let creating = self.creating;
+        let task = cx.background_executor().spawn(async move {
+            let vault = Vault::unlock(&passphrase)?;
+            if creating {
+                vault.save()?;
+            }
+            anyhow::Ok(vault)
+        });
+
+        cx.spawn(|view: WeakEntity<UnlockView>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let result = task.await;
+                view.update(&mut cx, |this, cx| {
+                    this.unlocking = false;
+                    match result {
+                        Ok(vault) => {
+                            cx.set_global(UnlockedVault(vault));
+                            cx.emit(Events::ViewChanged(Views::HomeView));
+                        }
+                        Err(e) => this.error = Some(format!("{:#}", e)),
+                    }
+                    cx.notify();
+                })
+                .ok();
+            }
+        })
+        .detach();
+    }
+}
+
+impl EventEmitter<Events> for UnlockView {}
+
+impl Render for UnlockView {
+    fn render(
+        &mut self,
+        _window: &mut gpui::Window,
+        cx: &mut gpui::Context<Self>,
+    ) -> impl gpui::IntoElement {
+        div()
+            .size_full()
+            .bg(rgb(0x18181b))
+            .flex()
+            .flex_col()
+            .justify_center()
+            .p_6()
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_4()
+                    .p_4()
+                    .max_w(px(360.0))
+                    .mx_auto()
+                    .w_full()
+                    .bg(rgb(0x27272a))
+                    .rounded_lg()
+                    .child(
+                        Label::new(if self.creating {
+                            "设置主密码"
+                        } else {
+                            "解锁密码库"
+                        })
+                        .text_xl()
+                        .font_semibold()
+                        .text_color(rgb(0xe4e4e7)),
+                    )
+                    .child(div().text_xs().text_color(rgb(0x71717a)).child(
+                        if self.creating {
+                            "邮箱密码将用主密码加密保存，不再以明文写入配置文件。请牢记主密码，遗忘后只能重新填写各账号密码。"
+                        } else {
+                            "输入主密码以读取已保存的邮箱密码。"
+                        },
+                    ))
+                    .child(Input::new(&self.passphrase))
+                    .when(self.creating, |this| this.child(Input::new(&self.confirm)))
+                    .when_some(self.error.clone(), |this, error| {
+                        this.child(div().text_sm().text_color(rgb(0xf87171)).child(error))
+                    })
+                    .child(
+                        Button::new("unlock-btn")
+                            .label(if self.unlocking { "解锁中..." } else { "确定" })
+                            .disabled(self.unlocking)
+                            .on_click(cx.listener(|view, _, _, cx| view.unlock(cx))),
+                    ),
+            )
+    }
+}